@@ -0,0 +1,75 @@
+pub const NR10_ADDR: u16 = 0xFF10;
+pub const NR51_ADDR: u16 = 0xFF25;
+pub const NR52_ADDR: u16 = 0xFF26;
+pub const WAVE_RAM_ADDR: u16 = 0xFF30;
+pub const WAVE_RAM_END: u16 = 0xFF3F;
+
+const NR52_POWER: u8 = 1 << 7;
+
+// Bits of NR10-NR52 that can't be read back and read as 1, indexed from FF10.
+// FF15 and FF1F don't exist.
+const READ_MASKS: [u8; 0x17] = [
+  0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+  0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR21-NR24
+  0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+  0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR41-NR44
+  0x00, 0x00, 0x70, // NR50-NR52
+];
+
+/// The sound registers NR10-NR52 (FF10-FF26) and wave RAM (FF30-FF3F).
+///
+/// No sound is generated yet: the registers hold what the CPU writes and
+/// read back with the hardware's write-only bits set. Turning the APU off
+/// with NR52 clears NR10-NR51 and ignores writes to them until it's turned
+/// back on. Wave RAM stays accessible either way.
+#[derive(Debug, Default)]
+pub struct Apu {
+  /// NR10-NR51, indexed from FF10.
+  pub registers: [u8; 0x16],
+  /// NR52 bit 7.
+  pub power: bool,
+  /// NR52 bits 0-3, channels 1-4 playing. Read only; nothing plays yet, so
+  /// only the post-boot state sets them.
+  pub channels: u8,
+  pub wave_ram: [u8; 0x10],
+}
+
+impl Apu {
+  pub fn new() -> Apu {
+    Apu::default()
+  }
+
+  /// Reads FF10-FF3F. The unused FF27-FF2F read 0xFF.
+  pub fn read(&self, addr: u16) -> u8 {
+    match addr {
+      NR10_ADDR..=NR51_ADDR => {
+        let index = (addr - NR10_ADDR) as usize;
+        self.registers[index] | READ_MASKS[index]
+      }
+      NR52_ADDR => {
+        let power = if self.power { NR52_POWER } else { 0 };
+        power | self.channels | READ_MASKS[(NR52_ADDR - NR10_ADDR) as usize]
+      }
+      WAVE_RAM_ADDR..=WAVE_RAM_END => self.wave_ram[(addr - WAVE_RAM_ADDR) as usize],
+      _ => 0xFF,
+    }
+  }
+
+  /// Writes FF10-FF3F.
+  pub fn write(&mut self, addr: u16, data: u8) {
+    match addr {
+      NR10_ADDR..=NR51_ADDR if self.power => {
+        self.registers[(addr - NR10_ADDR) as usize] = data;
+      }
+      NR52_ADDR => {
+        self.power = data & NR52_POWER != 0;
+        if !self.power {
+          self.registers = [0; 0x16];
+          self.channels = 0;
+        }
+      }
+      WAVE_RAM_ADDR..=WAVE_RAM_END => self.wave_ram[(addr - WAVE_RAM_ADDR) as usize] = data,
+      _ => (),
+    }
+  }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::apu::NR52_ADDR;
+use crate::cpu::Cpu;
+use crate::dma::DMA_ADDR;
+use crate::memory::MemoryInterface;
+
+/// Writing a non-zero value unmaps the boot ROM until the next reset.
+pub const BOOT_ADDR: u16 = 0xFF50;
+
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+/// The CGB boot ROM is split around the cartridge header: 0000-00FF and
+/// 0200-08FF.
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+#[derive(Debug)]
+pub enum BootRomError {
+  Io(io::Error),
+  InvalidSize(usize),
+}
+
+impl fmt::Display for BootRomError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BootRomError::Io(e) => write!(f, "can't read boot rom: {}", e),
+      BootRomError::InvalidSize(len) => write!(
+        f,
+        "invalid boot rom size: {} bytes, expected {} (DMG) or {} (CGB)",
+        len, DMG_BOOT_ROM_SIZE, CGB_BOOT_ROM_SIZE
+      ),
+    }
+  }
+}
+
+impl std::error::Error for BootRomError {}
+
+impl From<io::Error> for BootRomError {
+  fn from(e: io::Error) -> Self {
+    BootRomError::Io(e)
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct BootRom {
+  data: Vec<u8>,
+}
+
+impl BootRom {
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<BootRom, BootRomError> {
+    BootRom::from_bytes(fs::read(path)?)
+  }
+
+  pub fn from_bytes(data: Vec<u8>) -> Result<BootRom, BootRomError> {
+    match data.len() {
+      DMG_BOOT_ROM_SIZE | CGB_BOOT_ROM_SIZE => Ok(BootRom { data }),
+      len => Err(BootRomError::InvalidSize(len)),
+    }
+  }
+
+  pub fn size(&self) -> usize {
+    self.data.len()
+  }
+
+  /// The byte the boot ROM overlays at `addr`, or None where the cartridge
+  /// shows through.
+  pub fn read(&self, addr: u16) -> Option<u8> {
+    match addr {
+      0x0000..=0x00FF | 0x0200..=0x08FF => self.data.get(addr as usize).copied(),
+      _ => None,
+    }
+  }
+}
+
+/// Puts the CPU and IO registers in the state the boot ROM of `bus.model`
+/// leaves them in when it jumps to the cartridge entry point at 0x0100.
+pub fn skip_boot(cpu: &mut Cpu) {
+  let bus = &mut cpu.bus;
+  bus.boot_rom = None;
+  for (addr, data) in bus.model.post_boot_io() {
+    match addr {
+      // The value left in FF46, not a transfer.
+      DMA_ADDR => bus.dma.source = data,
+      // Channel 1 is still playing the boot sound on DMG.
+      NR52_ADDR => {
+        bus.write(addr, data);
+        bus.apu.channels = data & 0x0F;
+      }
+      _ => bus.write(addr, data),
+    }
+  }
+  bus.interrupts.enable = 0x00;
+  bus.timer.divider = bus.model.post_boot_divider();
+
+  cpu.reg = bus.model.post_boot_registers(&bus.cartridge.header);
+}
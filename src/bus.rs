@@ -1,32 +1,188 @@
-#![allow(dead_code)]
+use crate::apu::{Apu, NR10_ADDR, WAVE_RAM_END};
+use crate::boot::{BootRom, BOOT_ADDR};
+use crate::cartridge::Cartridge;
+use crate::dma::{Dma, DMA_ADDR};
+use crate::interrupts::{InterruptController, IE_ADDR, IF_ADDR};
+use crate::memory::MemoryInterface;
+use crate::model::Model;
+use crate::ppu::{Ppu, BGP_ADDR, LCDC_ADDR, LYC_ADDR, WX_ADDR};
+use crate::timer::{Timer, DIV_ADDR, TAC_ADDR};
 
-use std::ptr::null_mut;
+const P1_ADDR: u16 = 0xFF00;
+const SB_ADDR: u16 = 0xFF01;
+const SC_ADDR: u16 = 0xFF02;
+// P1 bits 4 and 5 select the d-pad or the buttons.
+const P1_SELECT: u8 = 0x30;
 
-use crate::cpu::Cpu;
+// Registers that only exist on CGB hardware. On the other models they're
+// unmapped like the rest of the IO range without hardware behind it.
+const KEY1_ADDR: u16 = 0xFF4D;
+const VBK_ADDR: u16 = 0xFF4F;
+const RP_ADDR: u16 = 0xFF56;
+const SVBK_ADDR: u16 = 0xFF70;
 
+/// The CPU address space and the hardware behind it.
+///
+/// ```text
+/// 0000-7FFF  cartridge ROM, read only. The boot ROM is overlaid at 0000-00FF
+///            (and 0200-08FF on CGB) until a write to FF50.
+/// 8000-9FFF  video RAM
+/// A000-BFFF  external (cartridge) RAM
+/// C000-DFFF  work RAM
+/// E000-FDFF  echo of C000-DDFF
+/// FE00-FE9F  object attribute memory (OAM)
+/// FEA0-FEFF  not usable, reads return 0x00 and writes are ignored
+/// FF00-FF7F  IO registers
+/// FF80-FFFE  high RAM
+/// FFFF       interrupt enable register
+/// ```
 #[derive(Debug)]
 pub struct Bus {
-  pub memory: [u8; 0xffff],
-  cpu: *mut Cpu,
+  pub model: Model,
+  pub cartridge: Cartridge,
+  pub boot_rom: Option<BootRom>,
+  pub wram: [u8; 0x2000],
+  pub io: [u8; 0x80],
+  pub hram: [u8; 0x7F],
+  pub interrupts: InterruptController,
+  pub timer: Timer,
+  pub ppu: Ppu,
+  pub dma: Dma,
+  pub apu: Apu,
 }
 
 impl Bus {
   pub fn new() -> Bus {
     Bus {
-      memory: [0; 0xffff],
-      cpu: null_mut(),
+      model: Model::Dmg,
+      cartridge: Cartridge::empty(),
+      boot_rom: None,
+      wram: [0; 0x2000],
+      io: [0; 0x80],
+      hram: [0; 0x7F],
+      interrupts: InterruptController::new(),
+      timer: Timer::new(),
+      ppu: Ppu::new(),
+      dma: Dma::new(),
+      apu: Apu::new(),
     }
   }
 
-  pub fn cpu_connect(&mut self, cpu: &mut Cpu) {
-    self.cpu = cpu;
+  /// Replaces the cartridge, the rest of the hardware keeps its state.
+  pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+    self.cartridge = cartridge;
   }
 
-  pub fn read(&self, addr: u16) -> u8 {
-    self.memory[addr as usize]
+  fn read_io(&self, addr: u16) -> u8 {
+    match addr {
+      DIV_ADDR..=TAC_ADDR => self.timer.read(addr),
+      IF_ADDR => self.interrupts.read_flag(),
+      NR10_ADDR..=WAVE_RAM_END => self.apu.read(addr),
+      LCDC_ADDR..=LYC_ADDR | BGP_ADDR..=WX_ADDR => self.ppu.read(addr),
+      DMA_ADDR => self.dma.source,
+      BOOT_ADDR => 0xFF,
+      // There's no input yet, so no button is pressed on either line. Bits
+      // 6 and 7 are unused and read as 1.
+      P1_ADDR => 0xC0 | (self.io[0] & P1_SELECT) | 0x0F,
+      SB_ADDR | SC_ADDR => self.io[(addr - 0xFF00) as usize],
+      KEY1_ADDR | VBK_ADDR | RP_ADDR | SVBK_ADDR if self.model.is_cgb() => {
+        self.io[(addr - 0xFF00) as usize]
+      }
+      // Unmapped.
+      _ => 0xFF,
+    }
+  }
+
+  /// What the DMA reads at `addr`. It isn't blocked by the PPU, and sources
+  /// from E000 up read work RAM.
+  fn dma_read(&self, addr: u16) -> u8 {
+    match addr {
+      0x8000..=0x9FFF => self.ppu.vram[(addr - 0x8000) as usize],
+      0xE000..=0xFFFF => self.wram[(addr as usize - 0xE000) & 0x1FFF],
+      _ => self.read(addr),
+    }
+  }
+
+  fn write_io(&mut self, addr: u16, data: u8) {
+    match addr {
+      DIV_ADDR..=TAC_ADDR => self.timer.write(addr, data),
+      IF_ADDR => self.interrupts.write_flag(data),
+      NR10_ADDR..=WAVE_RAM_END => self.apu.write(addr, data),
+      LCDC_ADDR..=LYC_ADDR | BGP_ADDR..=WX_ADDR => self.ppu.write(addr, data),
+      DMA_ADDR => self.dma.start(data),
+      BOOT_ADDR if data != 0 => self.boot_rom = None,
+      BOOT_ADDR => (),
+      P1_ADDR => self.io[0] = data & P1_SELECT,
+      SB_ADDR | SC_ADDR => self.io[(addr - 0xFF00) as usize] = data,
+      KEY1_ADDR | VBK_ADDR | RP_ADDR | SVBK_ADDR if self.model.is_cgb() => {
+        self.io[(addr - 0xFF00) as usize] = data
+      }
+      _ => (),
+    }
+  }
+}
+
+impl Default for Bus {
+  fn default() -> Bus {
+    Bus::new()
+  }
+}
+
+impl MemoryInterface for Bus {
+  fn tick(&mut self, t_cycles: usize) {
+    self.timer.tick(t_cycles, &mut self.interrupts);
+    self.ppu.tick(t_cycles, &mut self.interrupts);
+
+    for _ in 0..t_cycles / 4 {
+      match self.dma.step() {
+        Some((source, index)) => self.ppu.oam[index] = self.dma_read(source),
+        None => break,
+      }
+    }
   }
 
-  pub fn write(&mut self, addr: u16, data: u8) {
-    self.memory[addr as usize] = data;
+  fn read(&self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x7FFF => match self.boot_rom.as_ref().and_then(|boot| boot.read(addr)) {
+        Some(data) => data,
+        None => self.cartridge.read(addr),
+      },
+      0x8000..=0x9FFF => self.ppu.read_vram(addr),
+      0xA000..=0xBFFF => self.cartridge.read(addr),
+      0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize],
+      0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize],
+      0xFE00..=0xFE9F if self.dma.active() => 0xFF,
+      0xFE00..=0xFE9F => self.ppu.read_oam(addr),
+      0xFEA0..=0xFEFF => 0x00,
+      0xFF00..=0xFF7F => self.read_io(addr),
+      0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize],
+      IE_ADDR => self.interrupts.enable,
+    }
+  }
+
+  fn write(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x7FFF => self.cartridge.write(addr, data),
+      0x8000..=0x9FFF => self.ppu.write_vram(addr, data),
+      0xA000..=0xBFFF => self.cartridge.write(addr, data),
+      0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize] = data,
+      0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize] = data,
+      0xFE00..=0xFE9F if self.dma.active() => (),
+      0xFE00..=0xFE9F => self.ppu.write_oam(addr, data),
+      0xFEA0..=0xFEFF => (),
+      0xFF00..=0xFF7F => self.write_io(addr, data),
+      0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize] = data,
+      IE_ADDR => self.interrupts.enable = data,
+    }
+  }
+
+  fn poke(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x7FFF => self.cartridge.poke(addr, data),
+      // Ignores the PPU blocking VRAM and OAM.
+      0x8000..=0x9FFF => self.ppu.vram[(addr - 0x8000) as usize] = data,
+      0xFE00..=0xFE9F => self.ppu.oam[(addr - 0xFE00) as usize] = data,
+      _ => self.write(addr, data),
+    }
   }
 }
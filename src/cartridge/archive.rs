@@ -0,0 +1,219 @@
+use std::fmt;
+use std::io::{self, Read};
+
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+
+use crate::cartridge::MAX_ROM_SIZE;
+use crate::utils::crc32::crc32;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+const ZIP_LOCAL_HEADER: u32 = 0x04034B50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014B50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x06054B50;
+
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+  /// Broken zip structure or compressed stream.
+  Corrupt(String),
+  /// The zip doesn't contain a .gb or .gbc file.
+  NoRom,
+  EntryNotFound(String),
+  UnsupportedCompression(u16),
+  ChecksumMismatch {
+    expected: u32,
+    actual: u32,
+  },
+}
+
+impl fmt::Display for ArchiveError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ArchiveError::Corrupt(reason) => write!(f, "corrupt archive: {}", reason),
+      ArchiveError::NoRom => write!(f, "no .gb or .gbc file in archive"),
+      ArchiveError::EntryNotFound(name) => write!(f, "{} not found in archive", name),
+      ArchiveError::UnsupportedCompression(method) => {
+        write!(f, "unsupported zip compression method: {}", method)
+      }
+      ArchiveError::ChecksumMismatch { expected, actual } => write!(
+        f,
+        "archive checksum mismatch: expected crc32 {:08X}, got {:08X}",
+        expected, actual
+      ),
+    }
+  }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<io::Error> for ArchiveError {
+  fn from(e: io::Error) -> Self {
+    ArchiveError::Corrupt(e.to_string())
+  }
+}
+
+/// Unpacks zip and gzip files, told apart by their magic number. Anything
+/// else is returned unchanged. For zip files `entry` selects the file by
+/// name, otherwise the first .gb or .gbc file is used.
+pub fn extract(data: Vec<u8>, entry: Option<&str>) -> Result<Vec<u8>, ArchiveError> {
+  if data.starts_with(ZIP_MAGIC) {
+    extract_zip(&data, entry)
+  } else if data.starts_with(GZIP_MAGIC) {
+    let mut rom = Vec::new();
+    MultiGzDecoder::new(&data[..])
+      .take(MAX_ROM_SIZE as u64 + 1)
+      .read_to_end(&mut rom)?;
+    if rom.len() > MAX_ROM_SIZE {
+      return Err(ArchiveError::Corrupt("rom larger than 8 MiB".to_string()));
+    }
+    Ok(rom)
+  } else {
+    Ok(data)
+  }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, ArchiveError> {
+  data
+    .get(offset..offset + 2)
+    .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    .ok_or_else(|| ArchiveError::Corrupt("unexpected end of file".to_string()))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, ArchiveError> {
+  data
+    .get(offset..offset + 4)
+    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    .ok_or_else(|| ArchiveError::Corrupt("unexpected end of file".to_string()))
+}
+
+/// File in the zip central directory.
+#[derive(Debug)]
+struct ZipEntry {
+  name: String,
+  method: u16,
+  crc32: u32,
+  compressed_size: usize,
+  size: usize,
+  local_header: usize,
+}
+
+impl ZipEntry {
+  fn is_rom(&self) -> bool {
+    let name = self.name.to_ascii_lowercase();
+    name.ends_with(".gb") || name.ends_with(".gbc")
+  }
+
+  /// Matches the full path in the archive or just the file name.
+  fn matches(&self, name: &str) -> bool {
+    self.name == name || self.name.rsplit('/').next() == Some(name)
+  }
+}
+
+/// The end of central directory record is the last thing in the file,
+/// followed only by a comment of up to 64 KiB.
+fn zip_entries(data: &[u8]) -> Result<Vec<ZipEntry>, ArchiveError> {
+  let end = (0..data.len().saturating_sub(21))
+    .rev()
+    .take(0x10000 + 22)
+    .find(|&i| u32_at(data, i).ok() == Some(ZIP_END_OF_CENTRAL_DIRECTORY))
+    .ok_or_else(|| ArchiveError::Corrupt("missing central directory".to_string()))?;
+
+  let count = u16_at(data, end + 10)? as usize;
+  let mut offset = u32_at(data, end + 16)? as usize;
+  let mut entries = Vec::with_capacity(count);
+
+  for _ in 0..count {
+    if u32_at(data, offset)? != ZIP_CENTRAL_HEADER {
+      return Err(ArchiveError::Corrupt("bad central directory entry".to_string()));
+    }
+
+    let name_len = u16_at(data, offset + 28)? as usize;
+    let extra_len = u16_at(data, offset + 30)? as usize;
+    let comment_len = u16_at(data, offset + 32)? as usize;
+    let name = data
+      .get(offset + 46..offset + 46 + name_len)
+      .ok_or_else(|| ArchiveError::Corrupt("unexpected end of file".to_string()))?;
+
+    entries.push(ZipEntry {
+      name: String::from_utf8_lossy(name).into_owned(),
+      method: u16_at(data, offset + 10)?,
+      crc32: u32_at(data, offset + 16)?,
+      compressed_size: u32_at(data, offset + 20)? as usize,
+      size: u32_at(data, offset + 24)? as usize,
+      local_header: u32_at(data, offset + 42)? as usize,
+    });
+
+    offset += 46 + name_len + extra_len + comment_len;
+  }
+
+  Ok(entries)
+}
+
+fn extract_zip(data: &[u8], name: Option<&str>) -> Result<Vec<u8>, ArchiveError> {
+  let entries = zip_entries(data)?;
+
+  let entry = match name {
+    Some(name) => entries
+      .iter()
+      .find(|entry| entry.matches(name))
+      .ok_or_else(|| ArchiveError::EntryNotFound(name.to_string()))?,
+    None => entries
+      .iter()
+      .find(|entry| entry.is_rom())
+      .ok_or(ArchiveError::NoRom)?,
+  };
+
+  // The sizes come from the archive, don't trust them with an allocation.
+  if entry.size > MAX_ROM_SIZE {
+    return Err(ArchiveError::Corrupt(format!(
+      "{} is {} bytes, larger than any rom",
+      entry.name, entry.size
+    )));
+  }
+
+  // The local header repeats the name and may have a different extra field.
+  let offset = entry.local_header;
+  if u32_at(data, offset)? != ZIP_LOCAL_HEADER {
+    return Err(ArchiveError::Corrupt("bad local file header".to_string()));
+  }
+  let start =
+    offset + 30 + u16_at(data, offset + 26)? as usize + u16_at(data, offset + 28)? as usize;
+  let compressed = data
+    .get(start..start + entry.compressed_size)
+    .ok_or_else(|| ArchiveError::Corrupt("unexpected end of file".to_string()))?;
+
+  let rom = match entry.method {
+    ZIP_STORED => compressed.to_vec(),
+    ZIP_DEFLATED => {
+      let mut rom = Vec::with_capacity(entry.size);
+      DeflateDecoder::new(compressed)
+        .take(entry.size as u64 + 1)
+        .read_to_end(&mut rom)?;
+      rom
+    }
+    method => return Err(ArchiveError::UnsupportedCompression(method)),
+  };
+
+  if rom.len() != entry.size {
+    return Err(ArchiveError::Corrupt(format!(
+      "{} is {} bytes, expected {}",
+      entry.name,
+      rom.len(),
+      entry.size
+    )));
+  }
+
+  let actual = crc32(&rom);
+  if actual != entry.crc32 {
+    return Err(ArchiveError::ChecksumMismatch {
+      expected: entry.crc32,
+      actual,
+    });
+  }
+
+  Ok(rom)
+}
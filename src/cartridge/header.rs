@@ -0,0 +1,262 @@
+use std::fmt;
+
+use super::CartridgeError;
+
+pub const HEADER_END: usize = 0x0150;
+/// Largest ROM a header can declare, 8 MiB (ROM size code 0x08).
+pub const MAX_ROM_SIZE: usize = 0x8000 << 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapper {
+  RomOnly,
+  Mbc1,
+  Mbc2,
+  Mbc3,
+  Mbc5,
+  Mbc6,
+  Mbc7,
+  Mmm01,
+  PocketCamera,
+  BandaiTama5,
+  HuC3,
+  HuC1,
+}
+
+/// Cartridge type byte (0x147): the mapper plus the extra hardware on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CartridgeType {
+  pub code: u8,
+  pub mapper: Mapper,
+  pub ram: bool,
+  pub battery: bool,
+  pub timer: bool,
+  pub rumble: bool,
+}
+
+impl CartridgeType {
+  pub fn from_code(code: u8) -> Option<CartridgeType> {
+    let (mapper, ram, battery, timer, rumble) = match code {
+      0x00 => (Mapper::RomOnly, false, false, false, false),
+      0x01 => (Mapper::Mbc1, false, false, false, false),
+      0x02 => (Mapper::Mbc1, true, false, false, false),
+      0x03 => (Mapper::Mbc1, true, true, false, false),
+      0x05 => (Mapper::Mbc2, false, false, false, false),
+      0x06 => (Mapper::Mbc2, false, true, false, false),
+      0x08 => (Mapper::RomOnly, true, false, false, false),
+      0x09 => (Mapper::RomOnly, true, true, false, false),
+      0x0B => (Mapper::Mmm01, false, false, false, false),
+      0x0C => (Mapper::Mmm01, true, false, false, false),
+      0x0D => (Mapper::Mmm01, true, true, false, false),
+      0x0F => (Mapper::Mbc3, false, true, true, false),
+      0x10 => (Mapper::Mbc3, true, true, true, false),
+      0x11 => (Mapper::Mbc3, false, false, false, false),
+      0x12 => (Mapper::Mbc3, true, false, false, false),
+      0x13 => (Mapper::Mbc3, true, true, false, false),
+      0x19 => (Mapper::Mbc5, false, false, false, false),
+      0x1A => (Mapper::Mbc5, true, false, false, false),
+      0x1B => (Mapper::Mbc5, true, true, false, false),
+      0x1C => (Mapper::Mbc5, false, false, false, true),
+      0x1D => (Mapper::Mbc5, true, false, false, true),
+      0x1E => (Mapper::Mbc5, true, true, false, true),
+      0x20 => (Mapper::Mbc6, false, false, false, false),
+      0x22 => (Mapper::Mbc7, true, true, false, true),
+      0xFC => (Mapper::PocketCamera, false, false, false, false),
+      0xFD => (Mapper::BandaiTama5, false, false, false, false),
+      0xFE => (Mapper::HuC3, false, false, false, false),
+      0xFF => (Mapper::HuC1, true, true, false, false),
+      _ => return None,
+    };
+
+    Some(CartridgeType {
+      code,
+      mapper,
+      ram,
+      battery,
+      timer,
+      rumble,
+    })
+  }
+}
+
+impl fmt::Display for CartridgeType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mapper = match self.mapper {
+      Mapper::RomOnly => "ROM",
+      Mapper::Mbc1 => "MBC1",
+      Mapper::Mbc2 => "MBC2",
+      Mapper::Mbc3 => "MBC3",
+      Mapper::Mbc5 => "MBC5",
+      Mapper::Mbc6 => "MBC6",
+      Mapper::Mbc7 => "MBC7+SENSOR",
+      Mapper::Mmm01 => "MMM01",
+      Mapper::PocketCamera => "POCKET CAMERA",
+      Mapper::BandaiTama5 => "BANDAI TAMA5",
+      Mapper::HuC3 => "HuC3",
+      Mapper::HuC1 => "HuC1",
+    };
+
+    write!(f, "{}", mapper)?;
+    if self.timer {
+      write!(f, "+TIMER")?;
+    }
+    if self.rumble {
+      write!(f, "+RUMBLE")?;
+    }
+    if self.ram {
+      write!(f, "+RAM")?;
+    }
+    if self.battery {
+      write!(f, "+BATTERY")?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbFlag {
+  /// No CGB support (old DMG cartridge).
+  None,
+  /// Works on DMG, with CGB enhancements (0x80).
+  Supported,
+  /// CGB only (0xC0).
+  Only,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+  Japan,
+  Overseas,
+}
+
+/// Cartridge header, 0x0100-0x014F.
+#[derive(Debug, Clone)]
+pub struct CartridgeHeader {
+  pub entry_point: [u8; 4],
+  pub logo: [u8; 48],
+  pub title: String,
+  pub manufacturer_code: Option<String>,
+  pub cgb_flag: CgbFlag,
+  pub licensee_code: String,
+  pub sgb_flag: bool,
+  pub cartridge_type: CartridgeType,
+  pub rom_size: usize,
+  pub ram_size: usize,
+  pub destination: Destination,
+  pub version: u8,
+  pub header_checksum: u8,
+  pub global_checksum: u16,
+  /// Checksums computed from the ROM data.
+  pub computed_header_checksum: u8,
+  pub computed_global_checksum: u16,
+}
+
+impl CartridgeHeader {
+  pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, CartridgeError> {
+    if rom.len() < HEADER_END {
+      return Err(CartridgeError::TooSmall(rom.len()));
+    }
+
+    let cgb_flag = match rom[0x143] {
+      0xC0 => CgbFlag::Only,
+      flag if flag & 0x80 != 0 => CgbFlag::Supported,
+      _ => CgbFlag::None,
+    };
+
+    // On newer cartridges the title shrinks to make room for the
+    // manufacturer code (0x13F-0x142) and the CGB flag (0x143).
+    let (title, manufacturer_code) = match cgb_flag {
+      CgbFlag::None => (ascii(&rom[0x134..0x144]), None),
+      _ => (ascii(&rom[0x134..0x13F]), Some(ascii(&rom[0x13F..0x143]))),
+    };
+
+    // The new licensee code is only used when the old one is 0x33.
+    let licensee_code = match rom[0x14B] {
+      0x33 => ascii(&rom[0x144..0x146]),
+      code => format!("{:02X}", code),
+    };
+
+    let cartridge_type = CartridgeType::from_code(rom[0x147])
+      .ok_or(CartridgeError::UnknownCartridgeType(rom[0x147]))?;
+
+    let rom_size = match rom[0x148] {
+      code @ 0x00..=0x08 => 0x8000 << code,
+      code => return Err(CartridgeError::InvalidRomSize(code)),
+    };
+
+    let ram_size = match rom[0x149] {
+      0x00 => 0,
+      0x01 => 0x800,
+      0x02 => 0x2000,
+      0x03 => 0x8000,
+      0x04 => 0x20000,
+      0x05 => 0x10000,
+      code => return Err(CartridgeError::InvalidRamSize(code)),
+    };
+
+    let destination = match rom[0x14A] {
+      0x00 => Destination::Japan,
+      _ => Destination::Overseas,
+    };
+
+    let computed_header_checksum = rom[0x134..=0x14C]
+      .iter()
+      .fold(0u8, |x, &b| x.wrapping_sub(b).wrapping_sub(1));
+
+    let computed_global_checksum = rom
+      .iter()
+      .enumerate()
+      .filter(|&(i, _)| i != 0x14E && i != 0x14F)
+      .fold(0u16, |sum, (_, &b)| sum.wrapping_add(b as u16));
+
+    let mut entry_point = [0; 4];
+    entry_point.copy_from_slice(&rom[0x100..0x104]);
+
+    let mut logo = [0; 48];
+    logo.copy_from_slice(&rom[0x104..0x134]);
+
+    Ok(CartridgeHeader {
+      entry_point,
+      logo,
+      title,
+      manufacturer_code,
+      cgb_flag,
+      licensee_code,
+      sgb_flag: rom[0x146] == 0x03,
+      cartridge_type,
+      rom_size,
+      ram_size,
+      destination,
+      version: rom[0x14C],
+      header_checksum: rom[0x14D],
+      global_checksum: ((rom[0x14E] as u16) << 8) | rom[0x14F] as u16,
+      computed_header_checksum,
+      computed_global_checksum,
+    })
+  }
+
+  /// The boot ROM refuses to start a cartridge with a bad header checksum.
+  pub fn header_checksum_ok(&self) -> bool {
+    self.header_checksum == self.computed_header_checksum
+  }
+
+  /// Not verified by the hardware.
+  pub fn global_checksum_ok(&self) -> bool {
+    self.global_checksum == self.computed_global_checksum
+  }
+}
+
+fn ascii(bytes: &[u8]) -> String {
+  bytes
+    .iter()
+    .take_while(|&&b| b != 0)
+    .map(|&b| {
+      if b.is_ascii_graphic() || b == b' ' {
+        b as char
+      } else {
+        '.'
+      }
+    })
+    .collect::<String>()
+    .trim_end()
+    .to_string()
+}
@@ -0,0 +1,103 @@
+use super::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+/// MBC1: up to 2 MiB ROM and 32 KiB RAM.
+///
+/// ```text
+/// 0000-1FFF  RAM enable, 0x0A in the lower nibble enables
+/// 2000-3FFF  BANK1, lower 5 bits of the ROM bank. 0 is treated as 1, which
+///            is checked before BANK2 is applied, so banks 0x20, 0x40 and 0x60
+///            can't be mapped at 4000-7FFF.
+/// 4000-5FFF  BANK2, 2 bits: RAM bank or upper bits of the ROM bank
+/// 6000-7FFF  mode: 0 = BANK2 only applies to 4000-7FFF,
+///            1 = BANK2 also applies to 0000-3FFF and the RAM
+/// ```
+///
+/// MBC1M multicarts wire BANK2 to ROM address bits 18-19 instead of 19-20,
+/// so BANK1 only uses 4 bits.
+#[derive(Debug)]
+pub struct Mbc1 {
+  ram_enabled: bool,
+  bank1: u8,
+  bank2: u8,
+  mode: bool,
+  multicart: bool,
+}
+
+impl Mbc1 {
+  pub fn new(rom: &[u8]) -> Mbc1 {
+    Mbc1 {
+      ram_enabled: false,
+      bank1: 1,
+      bank2: 0,
+      mode: false,
+      multicart: Mbc1::is_multicart(rom),
+    }
+  }
+
+  /// MBC1M carts are 1 MiB and contain a second game, with its own Nintendo
+  /// logo, starting at bank 0x10.
+  pub fn is_multicart(rom: &[u8]) -> bool {
+    let logo = 0x104..0x134;
+    let second_game = 0x10 * ROM_BANK_SIZE;
+
+    rom.len() == 0x100000
+      && rom[logo.clone()] == rom[second_game + logo.start..second_game + logo.end]
+  }
+
+  fn bank2_shift(&self) -> u8 {
+    if self.multicart {
+      4
+    } else {
+      5
+    }
+  }
+
+  fn rom_bank_low(&self) -> usize {
+    if self.mode {
+      (self.bank2 << self.bank2_shift()) as usize
+    } else {
+      0
+    }
+  }
+
+  fn rom_bank_high(&self) -> usize {
+    let bank1 = if self.multicart {
+      self.bank1 & 0x0F
+    } else {
+      self.bank1
+    };
+    ((self.bank2 << self.bank2_shift()) | bank1) as usize
+  }
+}
+
+impl Mbc for Mbc1 {
+  fn rom_offset(&self, addr: u16) -> usize {
+    match addr {
+      0x0000..=0x3FFF => self.rom_bank_low() * ROM_BANK_SIZE + addr as usize,
+      _ => self.rom_bank_high() * ROM_BANK_SIZE + (addr - 0x4000) as usize,
+    }
+  }
+
+  fn ram_offset(&self, addr: u16) -> Option<usize> {
+    if !self.ram_enabled {
+      return None;
+    }
+
+    let bank = if self.mode { self.bank2 as usize } else { 0 };
+    Some(bank * RAM_BANK_SIZE + (addr - 0xA000) as usize)
+  }
+
+  fn write_register(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+      0x2000..=0x3FFF => {
+        self.bank1 = data & 0x1F;
+        if self.bank1 == 0 {
+          self.bank1 = 1;
+        }
+      }
+      0x4000..=0x5FFF => self.bank2 = data & 0b11,
+      _ => self.mode = data & 0b1 == 1,
+    }
+  }
+}
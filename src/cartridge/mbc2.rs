@@ -0,0 +1,76 @@
+use super::{Mbc, ROM_BANK_SIZE};
+
+/// Size of the built-in RAM, in half bytes.
+pub const MBC2_RAM_SIZE: usize = 512;
+
+/// MBC2: up to 256 KiB ROM and 512x4 bits of built-in RAM.
+///
+/// ```text
+/// 0000-3FFF  address bit 8 clear: RAM enable, 0x0A in the lower nibble enables
+///            address bit 8 set: ROM bank, 4 bits, 0 is treated as 1
+/// A000-BFFF  RAM, only 9 address bits are decoded so A000-A1FF repeats
+///            through the whole area. The upper nibble reads as 1s.
+/// ```
+#[derive(Debug)]
+pub struct Mbc2 {
+  ram_enabled: bool,
+  rom_bank: u8,
+}
+
+impl Mbc2 {
+  pub fn new() -> Mbc2 {
+    Mbc2 {
+      ram_enabled: false,
+      rom_bank: 1,
+    }
+  }
+}
+
+impl Default for Mbc2 {
+  fn default() -> Mbc2 {
+    Mbc2::new()
+  }
+}
+
+impl Mbc for Mbc2 {
+  fn rom_offset(&self, addr: u16) -> usize {
+    match addr {
+      0x0000..=0x3FFF => addr as usize,
+      _ => self.rom_bank as usize * ROM_BANK_SIZE + (addr - 0x4000) as usize,
+    }
+  }
+
+  fn ram_offset(&self, addr: u16) -> Option<usize> {
+    self.ram_enabled.then_some((addr & 0x01FF) as usize)
+  }
+
+  fn write_register(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x3FFF if addr & 0x0100 == 0 => self.ram_enabled = data & 0x0F == 0x0A,
+      0x0000..=0x3FFF => {
+        self.rom_bank = data & 0x0F;
+        if self.rom_bank == 0 {
+          self.rom_bank = 1;
+        }
+      }
+      _ => (),
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+    match self.ram_offset(addr) {
+      Some(offset) => ram[offset] | 0xF0,
+      None => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], addr: u16, data: u8) -> bool {
+    match self.ram_offset(addr) {
+      Some(offset) => {
+        ram[offset] = data & 0x0F;
+        true
+      }
+      None => false,
+    }
+  }
+}
@@ -0,0 +1,103 @@
+use super::rtc::{Clock, Rtc};
+use super::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+/// MBC3: up to 2 MiB ROM, 32 KiB RAM and an optional real time clock.
+///
+/// ```text
+/// 0000-1FFF  RAM and RTC enable, 0x0A in the lower nibble enables
+/// 2000-3FFF  ROM bank, 7 bits, 0 is treated as 1
+/// 4000-5FFF  0x00-0x03 selects a RAM bank, 0x08-0x0C an RTC register
+/// 6000-7FFF  writing 0x00 then 0x01 latches the RTC registers
+/// ```
+#[derive(Debug)]
+pub struct Mbc3 {
+  ram_enabled: bool,
+  rom_bank: u8,
+  select: u8,
+  pub rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+  pub fn new(clock: Option<Box<dyn Clock>>) -> Mbc3 {
+    Mbc3 {
+      ram_enabled: false,
+      rom_bank: 1,
+      select: 0,
+      rtc: clock.map(Rtc::new),
+    }
+  }
+
+  fn rtc_selected(&self) -> bool {
+    matches!(self.select, 0x08..=0x0C)
+  }
+}
+
+impl Mbc for Mbc3 {
+  fn rom_offset(&self, addr: u16) -> usize {
+    match addr {
+      0x0000..=0x3FFF => addr as usize,
+      _ => self.rom_bank as usize * ROM_BANK_SIZE + (addr - 0x4000) as usize,
+    }
+  }
+
+  fn ram_offset(&self, addr: u16) -> Option<usize> {
+    match self.select {
+      0x00..=0x03 if self.ram_enabled => {
+        Some(self.select as usize * RAM_BANK_SIZE + (addr - 0xA000) as usize)
+      }
+      _ => None,
+    }
+  }
+
+  fn write_register(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+      0x2000..=0x3FFF => {
+        self.rom_bank = data & 0x7F;
+        if self.rom_bank == 0 {
+          self.rom_bank = 1;
+        }
+      }
+      0x4000..=0x5FFF => self.select = data & 0x0F,
+      _ => {
+        if let Some(rtc) = &mut self.rtc {
+          rtc.write_latch(data);
+        }
+      }
+    }
+  }
+
+  fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+    self.rtc.as_mut()
+  }
+
+  fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+    match &self.rtc {
+      Some(rtc) if self.ram_enabled && self.rtc_selected() => rtc.read(self.select),
+      _ => match self.ram_offset(addr) {
+        Some(offset) if !ram.is_empty() => ram[offset % ram.len()],
+        _ => 0xFF,
+      },
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], addr: u16, data: u8) -> bool {
+    let rtc_selected = self.ram_enabled && self.rtc_selected();
+
+    match &mut self.rtc {
+      // The clock is saved on every flush, so it never dirties the RAM.
+      Some(rtc) if rtc_selected => {
+        rtc.write(self.select, data);
+        false
+      }
+      _ => match self.ram_offset(addr) {
+        Some(offset) if !ram.is_empty() => {
+          let len = ram.len();
+          ram[offset % len] = data;
+          true
+        }
+        _ => false,
+      },
+    }
+  }
+}
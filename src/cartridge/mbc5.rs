@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use super::{CartridgeEvent, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+/// MBC5: up to 8 MiB ROM (9-bit bank number) and 128 KiB RAM.
+///
+/// ```text
+/// 0000-1FFF  RAM enable, 0x0A in the lower nibble enables
+/// 2000-2FFF  lower 8 bits of the ROM bank, bank 0 can be mapped at 4000-7FFF
+/// 3000-3FFF  bit 8 of the ROM bank
+/// 4000-5FFF  RAM bank, 4 bits. On rumble carts bit 3 drives the motor
+///            and only bits 0-2 select the RAM bank.
+/// ```
+#[derive(Debug)]
+pub struct Mbc5 {
+  ram_enabled: bool,
+  rom_bank: u16,
+  ram_bank: u8,
+  has_rumble: bool,
+  rumble: bool,
+  events: VecDeque<CartridgeEvent>,
+}
+
+impl Mbc5 {
+  pub fn new(has_rumble: bool) -> Mbc5 {
+    Mbc5 {
+      ram_enabled: false,
+      rom_bank: 1,
+      ram_bank: 0,
+      has_rumble,
+      rumble: false,
+      events: VecDeque::new(),
+    }
+  }
+}
+
+impl Mbc for Mbc5 {
+  fn rom_offset(&self, addr: u16) -> usize {
+    match addr {
+      0x0000..=0x3FFF => addr as usize,
+      _ => self.rom_bank as usize * ROM_BANK_SIZE + (addr - 0x4000) as usize,
+    }
+  }
+
+  fn ram_offset(&self, addr: u16) -> Option<usize> {
+    self
+      .ram_enabled
+      .then(|| self.ram_bank as usize * RAM_BANK_SIZE + (addr - 0xA000) as usize)
+  }
+
+  fn write_register(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+      0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | data as u16,
+      0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((data as u16 & 0b1) << 8),
+      0x4000..=0x5FFF => {
+        if self.has_rumble {
+          self.ram_bank = data & 0x07;
+
+          let rumble = data & 0x08 != 0;
+          if rumble != self.rumble {
+            self.rumble = rumble;
+            self.events.push_back(CartridgeEvent::Rumble(rumble));
+          }
+        } else {
+          self.ram_bank = data & 0x0F;
+        }
+      }
+      _ => (),
+    }
+  }
+
+  fn poll_event(&mut self) -> Option<CartridgeEvent> {
+    self.events.pop_front()
+  }
+}
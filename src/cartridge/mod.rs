@@ -0,0 +1,257 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub mod archive;
+mod header;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+pub mod patch;
+mod rom_only;
+mod rtc;
+mod save;
+
+pub use archive::ArchiveError;
+pub use header::{CartridgeHeader, CartridgeType, CgbFlag, Destination, Mapper, MAX_ROM_SIZE};
+pub use mbc1::Mbc1;
+pub use mbc2::{Mbc2, MBC2_RAM_SIZE};
+pub use mbc3::Mbc3;
+pub use mbc5::Mbc5;
+pub use patch::{find_patches, PatchError};
+pub use rom_only::RomOnly;
+pub use rtc::{Clock, Rtc, SystemClock};
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+  Io(io::Error),
+  /// The file is smaller than the cartridge header.
+  TooSmall(usize),
+  UnknownCartridgeType(u8),
+  InvalidRomSize(u8),
+  InvalidRamSize(u8),
+  UnsupportedCartridgeType(CartridgeType),
+  Archive(ArchiveError),
+  Patch(PathBuf, PatchError),
+}
+
+impl fmt::Display for CartridgeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CartridgeError::Io(e) => write!(f, "can't read rom: {}", e),
+      CartridgeError::TooSmall(len) => write!(f, "rom too small: {} bytes", len),
+      CartridgeError::UnknownCartridgeType(code) => {
+        write!(f, "unknown cartridge type: {:02X}", code)
+      }
+      CartridgeError::InvalidRomSize(code) => write!(f, "invalid rom size: {:02X}", code),
+      CartridgeError::InvalidRamSize(code) => write!(f, "invalid ram size: {:02X}", code),
+      CartridgeError::UnsupportedCartridgeType(cartridge_type) => {
+        write!(f, "unsupported cartridge type: {}", cartridge_type)
+      }
+      CartridgeError::Archive(e) => write!(f, "{}", e),
+      CartridgeError::Patch(path, e) => write!(f, "{}: {}", path.display(), e),
+    }
+  }
+}
+
+impl std::error::Error for CartridgeError {}
+
+impl From<ArchiveError> for CartridgeError {
+  fn from(e: ArchiveError) -> Self {
+    CartridgeError::Archive(e)
+  }
+}
+
+impl From<io::Error> for CartridgeError {
+  fn from(e: io::Error) -> Self {
+    CartridgeError::Io(e)
+  }
+}
+
+/// Cartridge hardware state changes the frontend may want to react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartridgeEvent {
+  /// Rumble motor turned on (true) or off (false).
+  Rumble(bool),
+}
+
+/// Memory bank controller. Maps CPU addresses to offsets in the ROM image and
+/// the cartridge RAM, and handles writes to its control registers.
+pub trait Mbc: fmt::Debug {
+  /// Offset into the ROM image for an address in 0x0000-0x7FFF.
+  fn rom_offset(&self, addr: u16) -> usize;
+
+  /// Offset into the cartridge RAM for an address in 0xA000-0xBFFF, or None
+  /// when the RAM is disabled.
+  fn ram_offset(&self, addr: u16) -> Option<usize>;
+
+  /// Write to 0x0000-0x7FFF.
+  fn write_register(&mut self, addr: u16, data: u8);
+
+  fn poll_event(&mut self) -> Option<CartridgeEvent> {
+    None
+  }
+
+  fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+    None
+  }
+
+  fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+    match self.ram_offset(addr) {
+      Some(offset) if !ram.is_empty() => ram[offset % ram.len()],
+      _ => 0xFF,
+    }
+  }
+
+  /// Write to 0xA000-0xBFFF. Returns whether a byte of `ram` was stored, so
+  /// writes while the RAM is disabled don't count as changes to save.
+  fn write_ram(&mut self, ram: &mut [u8], addr: u16, data: u8) -> bool {
+    match self.ram_offset(addr) {
+      Some(offset) if !ram.is_empty() => {
+        let len = ram.len();
+        ram[offset % len] = data;
+        true
+      }
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+  /// Patches applied to the ROM, in order.
+  pub patches: Vec<PathBuf>,
+  /// File to load from a zip archive instead of the first .gb or .gbc.
+  pub archive_entry: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Cartridge {
+  pub header: CartridgeHeader,
+  pub rom: Vec<u8>,
+  pub ram: Vec<u8>,
+  pub mbc: Box<dyn Mbc>,
+  /// Set when a RAM byte is stored, cleared when it is saved.
+  pub ram_dirty: bool,
+}
+
+impl Cartridge {
+  /// Loads a ROM, zip or gzip file without patches.
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Cartridge, CartridgeError> {
+    Cartridge::from_file_with_options(path, &LoadOptions::default())
+  }
+
+  /// Loads the ROM, unpacking it if it's a zip or gzip file, and applies the
+  /// patches in order. Everything happens in memory, the files on disk are
+  /// left untouched.
+  pub fn from_file_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &LoadOptions,
+  ) -> Result<Cartridge, CartridgeError> {
+    let data = fs::read(path)?;
+    let mut rom = archive::extract(data, options.archive_entry.as_deref())?;
+
+    for patch in &options.patches {
+      let data = fs::read(patch)?;
+      rom = patch::apply(&rom, &data).map_err(|e| CartridgeError::Patch(patch.clone(), e))?;
+    }
+
+    Cartridge::from_bytes(rom)
+  }
+
+  /// Parses the header of `rom` and sets up its mapper and RAM.
+  pub fn from_bytes(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+    Cartridge::from_bytes_with_clock(rom, Box::new(SystemClock))
+  }
+
+  /// Like `from_bytes`, with the time source used by the cartridge RTC.
+  pub fn from_bytes_with_clock(
+    mut rom: Vec<u8>,
+    clock: Box<dyn Clock>,
+  ) -> Result<Cartridge, CartridgeError> {
+    let header = CartridgeHeader::parse(&rom)?;
+
+    if !header.header_checksum_ok() {
+      log::warn!(
+        "header checksum mismatch: expected {:02X}, got {:02X}",
+        header.header_checksum,
+        header.computed_header_checksum
+      );
+    }
+
+    // Pad to the size declared in the header so every bank is addressable.
+    if rom.len() < header.rom_size {
+      rom.resize(header.rom_size, 0xFF);
+    }
+
+    // MBC2 RAM is built into the controller and not declared in the header.
+    let ram = match header.cartridge_type.mapper {
+      Mapper::Mbc2 => vec![0; MBC2_RAM_SIZE],
+      _ => vec![0; header.ram_size],
+    };
+
+    let mbc: Box<dyn Mbc> = match header.cartridge_type.mapper {
+      Mapper::RomOnly => Box::new(RomOnly::new()),
+      Mapper::Mbc1 => Box::new(Mbc1::new(&rom)),
+      Mapper::Mbc2 => Box::new(Mbc2::new()),
+      Mapper::Mbc3 => {
+        let clock = header.cartridge_type.timer.then_some(clock);
+        Box::new(Mbc3::new(clock))
+      }
+      Mapper::Mbc5 => Box::new(Mbc5::new(header.cartridge_type.rumble)),
+      _ => return Err(CartridgeError::UnsupportedCartridgeType(header.cartridge_type)),
+    };
+
+    Ok(Cartridge {
+      header,
+      rom,
+      ram,
+      mbc,
+      ram_dirty: false,
+    })
+  }
+
+  /// A blank 32 KiB ROM without RAM, used when no cartridge is inserted.
+  pub fn empty() -> Cartridge {
+    Cartridge::from_bytes(vec![0; 0x8000]).unwrap()
+  }
+
+  /// CPU read from 0x0000-0x7FFF or 0xA000-0xBFFF.
+  pub fn read(&self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x7FFF => self.rom[self.mbc.rom_offset(addr) % self.rom.len()],
+      0xA000..=0xBFFF => self.mbc.read_ram(&self.ram, addr),
+      _ => 0xFF,
+    }
+  }
+
+  /// CPU write to the mapper registers (0x0000-0x7FFF) or the RAM.
+  pub fn write(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x7FFF => self.mbc.write_register(addr, data),
+      0xA000..=0xBFFF => self.ram_dirty |= self.mbc.write_ram(&mut self.ram, addr, data),
+      _ => (),
+    }
+  }
+
+  /// Next pending hardware event, if any.
+  pub fn poll_event(&mut self) -> Option<CartridgeEvent> {
+    self.mbc.poll_event()
+  }
+
+  /// Debugger write into the ROM bank currently mapped at `addr`.
+  pub fn poke(&mut self, addr: u16, data: u8) {
+    match addr {
+      0x0000..=0x7FFF => {
+        let offset = self.mbc.rom_offset(addr) % self.rom.len();
+        self.rom[offset] = data;
+      }
+      _ => self.write(addr, data),
+    }
+  }
+}
@@ -0,0 +1,353 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::cartridge::MAX_ROM_SIZE;
+use crate::utils::crc32::crc32;
+
+/// ROM patch formats, told apart by their magic number.
+///
+/// ```text
+/// IPS  "PATCH", records of (offset u24 BE, size u16 BE, data), a size of 0
+///      is a run of (count u16 BE, value). Ends with "EOF", optionally
+///      followed by the u24 size to truncate the ROM to.
+/// UPS  "UPS1", source and target sizes, then hunks of (skip, XOR bytes up to
+///      a 0x00) and the CRC32 of the source, target and patch.
+/// BPS  "BPS1", source, target and metadata sizes, then actions copying from
+///      the source, the patch or the target, and the same CRC32 footer.
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+  Ips,
+  Ups,
+  Bps,
+}
+
+impl PatchFormat {
+  pub const ALL: [PatchFormat; 3] = [PatchFormat::Ips, PatchFormat::Ups, PatchFormat::Bps];
+
+  pub fn detect(patch: &[u8]) -> Option<PatchFormat> {
+    PatchFormat::ALL
+      .into_iter()
+      .find(|format| patch.starts_with(format.magic()))
+  }
+
+  fn magic(&self) -> &'static [u8] {
+    match self {
+      PatchFormat::Ips => b"PATCH",
+      PatchFormat::Ups => b"UPS1",
+      PatchFormat::Bps => b"BPS1",
+    }
+  }
+
+  pub fn extension(&self) -> &'static str {
+    match self {
+      PatchFormat::Ips => "ips",
+      PatchFormat::Ups => "ups",
+      PatchFormat::Bps => "bps",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+  UnknownFormat,
+  /// The patch ends in the middle of a record.
+  Truncated,
+  /// A BPS copy reads outside the source or target, or writes past the
+  /// target size.
+  OutOfBounds,
+  /// A UPS/BPS number doesn't fit in a usize.
+  Overflow,
+  /// The target is bigger than any Game Boy ROM.
+  TargetTooLarge(usize),
+  SourceChecksum {
+    expected: u32,
+    actual: u32,
+  },
+  TargetChecksum {
+    expected: u32,
+    actual: u32,
+  },
+  PatchChecksum {
+    expected: u32,
+    actual: u32,
+  },
+}
+
+impl fmt::Display for PatchError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PatchError::UnknownFormat => write!(f, "unknown patch format"),
+      PatchError::Truncated => write!(f, "patch truncated"),
+      PatchError::OutOfBounds => write!(f, "patch copies out of bounds"),
+      PatchError::Overflow => write!(f, "patch number overflows"),
+      PatchError::TargetTooLarge(size) => {
+        write!(f, "patched rom too large: {} bytes, max {}", size, MAX_ROM_SIZE)
+      }
+      PatchError::SourceChecksum { expected, actual } => write!(
+        f,
+        "patch is for another rom: expected crc32 {:08X}, got {:08X}",
+        expected, actual
+      ),
+      PatchError::TargetChecksum { expected, actual } => write!(
+        f,
+        "patched rom checksum mismatch: expected crc32 {:08X}, got {:08X}",
+        expected, actual
+      ),
+      PatchError::PatchChecksum { expected, actual } => {
+        write!(f, "patch corrupted: expected crc32 {:08X}, got {:08X}", expected, actual)
+      }
+    }
+  }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Applies `patch` to a copy of `rom` and returns the patched ROM.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+  match PatchFormat::detect(patch) {
+    Some(PatchFormat::Ips) => apply_ips(rom, patch),
+    Some(PatchFormat::Ups) => apply_ups(rom, patch),
+    Some(PatchFormat::Bps) => apply_bps(rom, patch),
+    None => Err(PatchError::UnknownFormat),
+  }
+}
+
+/// Patches next to the ROM with the same name, e.g. game.gb -> game.ips
+pub fn find_patches<P: AsRef<Path>>(rom_path: P) -> Vec<PathBuf> {
+  PatchFormat::ALL
+    .iter()
+    .map(|format| rom_path.as_ref().with_extension(format.extension()))
+    .filter(|path| path.is_file())
+    .collect()
+}
+
+struct Reader<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn new(data: &'a [u8], pos: usize) -> Reader<'a> {
+    Reader { data, pos }
+  }
+
+  fn bytes(&mut self, n: usize) -> Result<&'a [u8], PatchError> {
+    let end = self.pos.checked_add(n).ok_or(PatchError::Truncated)?;
+    let bytes = self.data.get(self.pos..end).ok_or(PatchError::Truncated)?;
+    self.pos += n;
+    Ok(bytes)
+  }
+
+  fn byte(&mut self) -> Result<u8, PatchError> {
+    Ok(self.bytes(1)?[0])
+  }
+
+  fn be(&mut self, n: usize) -> Result<usize, PatchError> {
+    Ok(
+      self
+        .bytes(n)?
+        .iter()
+        .fold(0, |value, &b| (value << 8) | b as usize),
+    )
+  }
+
+  /// UPS/BPS variable length number: 7 bits per byte, least significant
+  /// first, bit 7 set on the last byte.
+  fn number(&mut self) -> Result<usize, PatchError> {
+    let mut value = 0usize;
+    let mut shift = 1usize;
+    loop {
+      let b = self.byte()?;
+      value = ((b & 0x7F) as usize)
+        .checked_mul(shift)
+        .and_then(|bits| value.checked_add(bits))
+        .ok_or(PatchError::Overflow)?;
+      if b & 0x80 != 0 {
+        return Ok(value);
+      }
+      shift = shift.checked_mul(0x80).ok_or(PatchError::Overflow)?;
+      value = value.checked_add(shift).ok_or(PatchError::Overflow)?;
+    }
+  }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+  let mut out = rom.to_vec();
+  let mut reader = Reader::new(patch, 5);
+
+  loop {
+    if reader.bytes(3)? == b"EOF" {
+      break;
+    }
+    reader.pos -= 3;
+
+    let offset = reader.be(3)?;
+    let (data, size) = match reader.be(2)? {
+      0 => {
+        let count = reader.be(2)?;
+        (None, count)
+      }
+      size => (Some(reader.bytes(size)?), size),
+    };
+
+    if out.len() < offset + size {
+      out.resize(offset + size, 0);
+    }
+    match data {
+      Some(data) => out[offset..offset + size].copy_from_slice(data),
+      None => out[offset..offset + size].fill(reader.byte()?),
+    }
+  }
+
+  // Truncation extension.
+  if let Ok(size) = reader.be(3) {
+    out.truncate(size);
+  }
+
+  Ok(out)
+}
+
+/// Checks the patch and source CRC32 at the end of UPS and BPS patches and
+/// returns the expected target CRC32.
+fn checksums(rom: &[u8], patch: &[u8]) -> Result<u32, PatchError> {
+  if patch.len() < 12 {
+    return Err(PatchError::Truncated);
+  }
+
+  let footer = &patch[patch.len() - 12..];
+  let crc = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+
+  let actual = crc32(&patch[..patch.len() - 4]);
+  if crc(8) != actual {
+    return Err(PatchError::PatchChecksum {
+      expected: crc(8),
+      actual,
+    });
+  }
+
+  let actual = crc32(rom);
+  if crc(0) != actual {
+    return Err(PatchError::SourceChecksum {
+      expected: crc(0),
+      actual,
+    });
+  }
+
+  Ok(crc(4))
+}
+
+fn check_target(out: &[u8], expected: u32) -> Result<(), PatchError> {
+  let actual = crc32(out);
+  if actual != expected {
+    return Err(PatchError::TargetChecksum { expected, actual });
+  }
+  Ok(())
+}
+
+/// Reads the UPS/BPS target size, which sizes the output buffer.
+fn read_target_size(reader: &mut Reader) -> Result<usize, PatchError> {
+  match reader.number()? {
+    size if size > MAX_ROM_SIZE => Err(PatchError::TargetTooLarge(size)),
+    size => Ok(size),
+  }
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+  let target_crc = checksums(rom, patch)?;
+  let body = &patch[..patch.len() - 12];
+  let mut reader = Reader::new(body, 4);
+
+  let _source_size = reader.number()?;
+  let target_size = read_target_size(&mut reader)?;
+
+  let mut out = rom.to_vec();
+  out.resize(target_size, 0);
+
+  let mut pos = 0;
+  while reader.pos < body.len() {
+    pos += reader.number()?;
+    loop {
+      let b = reader.byte()?;
+      if b == 0 {
+        break;
+      }
+      if let Some(out) = out.get_mut(pos) {
+        *out ^= b;
+      }
+      pos += 1;
+    }
+    // The terminating 0x00 also covers one byte.
+    pos += 1;
+  }
+
+  check_target(&out, target_crc)?;
+  Ok(out)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+  let target_crc = checksums(rom, patch)?;
+  let body = &patch[..patch.len() - 12];
+  let mut reader = Reader::new(body, 4);
+
+  let _source_size = reader.number()?;
+  let target_size = read_target_size(&mut reader)?;
+  let metadata_size = reader.number()?;
+  reader.bytes(metadata_size)?;
+
+  let mut out = Vec::with_capacity(target_size);
+  let mut source_offset = 0usize;
+  let mut target_offset = 0usize;
+
+  // Relative offsets are stored as magnitude << 1 | sign.
+  let relative = |offset: usize, data: usize| -> Result<usize, PatchError> {
+    let delta = data >> 1;
+    if data & 1 != 0 {
+      offset.checked_sub(delta).ok_or(PatchError::OutOfBounds)
+    } else {
+      offset.checked_add(delta).ok_or(PatchError::OutOfBounds)
+    }
+  };
+
+  while reader.pos < body.len() {
+    let data = reader.number()?;
+    let length = (data >> 2) + 1;
+    if out.len() + length > target_size {
+      return Err(PatchError::OutOfBounds);
+    }
+
+    match data & 0b11 {
+      // SourceRead: the source byte at the same position.
+      0 => {
+        let start = out.len();
+        let bytes = rom
+          .get(start..start + length)
+          .ok_or(PatchError::OutOfBounds)?;
+        out.extend_from_slice(bytes);
+      }
+      // TargetRead: bytes from the patch.
+      1 => out.extend_from_slice(reader.bytes(length)?),
+      // SourceCopy: anywhere in the source.
+      2 => {
+        source_offset = relative(source_offset, reader.number()?)?;
+        let bytes = rom
+          .get(source_offset..source_offset.saturating_add(length))
+          .ok_or(PatchError::OutOfBounds)?;
+        out.extend_from_slice(bytes);
+        source_offset += length;
+      }
+      // TargetCopy: earlier output, may overlap the bytes being written.
+      _ => {
+        target_offset = relative(target_offset, reader.number()?)?;
+        for _ in 0..length {
+          let b = *out.get(target_offset).ok_or(PatchError::OutOfBounds)?;
+          out.push(b);
+          target_offset += 1;
+        }
+      }
+    }
+  }
+
+  check_target(&out, target_crc)?;
+  Ok(out)
+}
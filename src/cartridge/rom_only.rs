@@ -0,0 +1,23 @@
+use super::Mbc;
+
+/// 32 KiB ROM with up to one 8 KiB RAM bank and no bank switching.
+#[derive(Debug, Default)]
+pub struct RomOnly;
+
+impl RomOnly {
+  pub fn new() -> RomOnly {
+    RomOnly
+  }
+}
+
+impl Mbc for RomOnly {
+  fn rom_offset(&self, addr: u16) -> usize {
+    addr as usize
+  }
+
+  fn ram_offset(&self, addr: u16) -> Option<usize> {
+    Some((addr - 0xA000) as usize)
+  }
+
+  fn write_register(&mut self, _addr: u16, _data: u8) {}
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of wall clock time for the cartridge RTC, in seconds.
+pub trait Clock: fmt::Debug {
+  fn now(&self) -> u64;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> u64 {
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or(0)
+  }
+}
+
+pub const DH_DAY_HIGH: u8 = 1 << 0;
+pub const DH_HALT: u8 = 1 << 6;
+pub const DH_DAY_CARRY: u8 = 1 << 7;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RtcRegisters {
+  pub seconds: u8,
+  pub minutes: u8,
+  pub hours: u8,
+  pub day_low: u8,
+  /// bit 0: day counter bit 8, bit 6: halt, bit 7: day counter carry
+  pub day_high: u8,
+}
+
+impl RtcRegisters {
+  pub fn days(&self) -> u16 {
+    (((self.day_high & DH_DAY_HIGH) as u16) << 8) | self.day_low as u16
+  }
+
+  pub fn halted(&self) -> bool {
+    self.day_high & DH_HALT != 0
+  }
+
+  /// Register selected by 0x08-0x0C in the RAM bank register.
+  pub fn get(&self, select: u8) -> u8 {
+    match select {
+      0x08 => self.seconds & 0x3F,
+      0x09 => self.minutes & 0x3F,
+      0x0A => self.hours & 0x1F,
+      0x0B => self.day_low,
+      _ => self.day_high & (DH_DAY_HIGH | DH_HALT | DH_DAY_CARRY),
+    }
+  }
+
+  pub fn set(&mut self, select: u8, data: u8) {
+    match select {
+      0x08 => self.seconds = data & 0x3F,
+      0x09 => self.minutes = data & 0x3F,
+      0x0A => self.hours = data & 0x1F,
+      0x0B => self.day_low = data,
+      _ => self.day_high = data & (DH_DAY_HIGH | DH_HALT | DH_DAY_CARRY),
+    }
+  }
+
+  fn advance(&mut self, seconds: u64) {
+    let total = self.seconds as u64 + seconds;
+    self.seconds = (total % 60) as u8;
+
+    let total = self.minutes as u64 + total / 60;
+    self.minutes = (total % 60) as u8;
+
+    let total = self.hours as u64 + total / 60;
+    self.hours = (total % 24) as u8;
+
+    let days = self.days() as u64 + total / 24;
+    if days > 0x1FF {
+      self.day_high |= DH_DAY_CARRY;
+    }
+
+    let days = days % 0x200;
+    self.day_low = (days & 0xFF) as u8;
+    self.day_high = (self.day_high & !DH_DAY_HIGH) | ((days >> 8) as u8 & DH_DAY_HIGH);
+  }
+}
+
+/// MBC3 real time clock. The live registers are brought up to date from the
+/// clock whenever they are accessed; the CPU only reads the latched copy.
+#[derive(Debug)]
+pub struct Rtc {
+  pub registers: RtcRegisters,
+  pub latched: RtcRegisters,
+  /// Clock time of the last update of `registers`.
+  pub timestamp: u64,
+  latch_armed: bool,
+  clock: Box<dyn Clock>,
+}
+
+impl Rtc {
+  pub fn new(clock: Box<dyn Clock>) -> Rtc {
+    let timestamp = clock.now();
+    Rtc {
+      registers: RtcRegisters::default(),
+      latched: RtcRegisters::default(),
+      timestamp,
+      latch_armed: false,
+      clock,
+    }
+  }
+
+  pub fn update(&mut self) {
+    let now = self.clock.now();
+    if !self.registers.halted() {
+      self.registers.advance(now.saturating_sub(self.timestamp));
+    }
+    self.timestamp = now;
+  }
+
+  /// Writing 0x00 and then 0x01 copies the live registers into the latched ones.
+  pub fn write_latch(&mut self, data: u8) {
+    if self.latch_armed && data == 0x01 {
+      self.update();
+      self.latched = self.registers;
+    }
+    self.latch_armed = data == 0x00;
+  }
+
+  pub fn read(&self, select: u8) -> u8 {
+    self.latched.get(select)
+  }
+
+  pub fn write(&mut self, select: u8, data: u8) {
+    self.update();
+    self.registers.set(select, data);
+  }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::rtc::{Rtc, RtcRegisters};
+use super::Cartridge;
+
+/// RTC trailer appended after the RAM by VBA-M, BGB, mGBA and others:
+/// the live and latched registers as 5 little endian u32 each, followed by
+/// the UNIX timestamp of the save as a little endian u64 (older files use a
+/// u32, making the trailer 44 bytes).
+pub const RTC_TRAILER_SIZE: usize = 48;
+const RTC_TRAILER_SIZE_32: usize = 44;
+
+impl Cartridge {
+  pub fn has_battery(&self) -> bool {
+    self.header.cartridge_type.battery
+  }
+
+  /// RAM contents followed by the RTC trailer, if the cartridge has an RTC.
+  pub fn save_data(&mut self) -> Vec<u8> {
+    let mut data = self.ram.clone();
+
+    if let Some(rtc) = self.mbc.rtc_mut() {
+      rtc.update();
+      data.extend_from_slice(&rtc_to_bytes(rtc));
+    }
+
+    data
+  }
+
+  pub fn load_save_data(&mut self, data: &[u8]) {
+    let len = self.ram.len().min(data.len());
+    self.ram[..len].copy_from_slice(&data[..len]);
+
+    if let Some(rtc) = self.mbc.rtc_mut() {
+      let trailer = &data[len..];
+      if trailer.len() == RTC_TRAILER_SIZE || trailer.len() == RTC_TRAILER_SIZE_32 {
+        rtc_from_bytes(rtc, trailer);
+      }
+    }
+  }
+
+  /// Loads `path` if it exists.
+  pub fn load_save<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+    match fs::read(path) {
+      Ok(data) => {
+        self.load_save_data(&data);
+        Ok(())
+      }
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// Writes the save file if the RAM changed since the last save. Cartridges
+  /// with an RTC are always written so the saved clock stays current.
+  pub fn flush_save<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+    let has_rtc = self.header.cartridge_type.timer;
+    if !self.has_battery() || !(self.ram_dirty || has_rtc) {
+      return Ok(());
+    }
+
+    let data = self.save_data();
+    fs::write(path, data)?;
+    self.ram_dirty = false;
+    Ok(())
+  }
+}
+
+fn rtc_to_bytes(rtc: &Rtc) -> [u8; RTC_TRAILER_SIZE] {
+  let mut bytes = [0; RTC_TRAILER_SIZE];
+
+  for (i, registers) in [rtc.registers, rtc.latched].iter().enumerate() {
+    let values = [
+      registers.seconds,
+      registers.minutes,
+      registers.hours,
+      registers.day_low,
+      registers.day_high,
+    ];
+    for (j, &value) in values.iter().enumerate() {
+      let offset = (i * 5 + j) * 4;
+      bytes[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+    }
+  }
+
+  bytes[40..48].copy_from_slice(&rtc.timestamp.to_le_bytes());
+  bytes
+}
+
+fn rtc_from_bytes(rtc: &mut Rtc, bytes: &[u8]) {
+  let value = |i: usize| bytes[i * 4];
+  let registers = |i: usize| RtcRegisters {
+    seconds: value(i),
+    minutes: value(i + 1),
+    hours: value(i + 2),
+    day_low: value(i + 3),
+    day_high: value(i + 4),
+  };
+
+  rtc.registers = registers(0);
+  rtc.latched = registers(5);
+
+  let mut timestamp = [0; 8];
+  timestamp[..bytes.len() - 40].copy_from_slice(&bytes[40..]);
+  rtc.timestamp = u64::from_le_bytes(timestamp);
+
+  // Catch up with the time that passed since the save.
+  rtc.update();
+}
@@ -1,9 +1,9 @@
-#![allow(dead_code)]
-use std::ptr::null_mut;
+use std::fmt;
 
 use crate::bus::Bus;
-
-use log;
+use crate::interrupts::{Interrupt, IE_ADDR, IF_ADDR};
+use crate::memory::MemoryInterface;
+use crate::timer::DIV_ADDR;
 
 pub enum Flags {
   Z, // Zero flag
@@ -32,8 +32,7 @@ impl Register {
   }
 
   pub fn get_bc(&self) -> u16 {
-    let register = ((self.b as u16) << 8) | self.c as u16;
-    register
+    ((self.b as u16) << 8) | self.c as u16
   }
 
   pub fn set_bc(&mut self, data: u16) {
@@ -43,8 +42,7 @@ impl Register {
     self.c = lo as u8;
   }
   pub fn get_de(&self) -> u16 {
-    let register = ((self.d as u16) << 8) | self.e as u16;
-    register
+    ((self.d as u16) << 8) | self.e as u16
   }
 
   pub fn set_de(&mut self, data: u16) {
@@ -54,8 +52,7 @@ impl Register {
     self.e = lo as u8;
   }
   pub fn get_hl(&self) -> u16 {
-    let register = ((self.h as u16) << 8) | self.l as u16;
-    register
+    ((self.h as u16) << 8) | self.l as u16
   }
 
   pub fn set_hl(&mut self, data: u16) {
@@ -64,36 +61,118 @@ impl Register {
     self.h = hi as u8;
     self.l = lo as u8;
   }
+
+  pub fn get_af(&self) -> u16 {
+    ((self.a as u16) << 8) | self.f as u16
+  }
+
+  /// The lower nibble of F is always zero.
+  pub fn set_af(&mut self, data: u16) {
+    let hi = (data >> 8) & 0xFF;
+    let lo = data & 0xF0;
+    self.a = hi as u8;
+    self.f = lo as u8;
+  }
 }
 
+/// Opcodes without an instruction. Executing one locks the CPU up until reset.
+pub const ILLEGAL_OPCODES: [u8; 11] = [
+  0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+  /// One of `ILLEGAL_OPCODES` was executed at `pc`, the CPU is locked up.
+  IllegalOpcode { opcode: u8, pc: u16 },
+}
+
+impl CpuError {
+  /// Address of the instruction that caused the error.
+  pub fn pc(&self) -> u16 {
+    match *self {
+      CpuError::IllegalOpcode { pc, .. } => pc,
+    }
+  }
+}
+
+impl fmt::Display for CpuError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CpuError::IllegalOpcode { opcode, pc } => {
+        write!(f, "illegal opcode {:02X} at {:04X}, cpu locked up", opcode, pc)
+      }
+    }
+  }
+}
+
+impl std::error::Error for CpuError {}
+
 #[derive(Debug)]
-pub struct Cpu {
+pub struct Cpu<B: MemoryInterface = Bus> {
   pub reg: Register,
-  bus: *mut Bus,
+  pub bus: B,
   pub cycles: usize,
+  pub ime: bool,
+  /// EI enables interrupts only after the following instruction.
+  pub ime_scheduled: bool,
+  pub halted: bool,
+  /// HALT with IME=0 and a pending interrupt fails to increment PC on the next fetch.
+  pub halt_bug: bool,
+  pub stopped: bool,
+  /// Set by an illegal opcode. Only a reset gets the CPU running again,
+  /// interrupts included.
+  pub locked: bool,
+  /// Address of the instruction being executed.
+  pub instruction_pc: u16,
+  // T-cycles of the current step the bus has already been ticked for.
+  ticked: usize,
 }
 
 impl Cpu {
   pub fn new() -> Cpu {
+    Cpu::with_bus(Bus::new())
+  }
+}
+
+impl Default for Cpu {
+  fn default() -> Cpu {
+    Cpu::new()
+  }
+}
+
+impl<B: MemoryInterface> Cpu<B> {
+  pub fn with_bus(bus: B) -> Cpu<B> {
     Cpu {
       reg: Register::new(),
-      bus: null_mut(),
+      bus,
       cycles: 0,
+      ime: false,
+      ime_scheduled: false,
+      halted: false,
+      halt_bug: false,
+      stopped: false,
+      locked: false,
+      instruction_pc: 0,
+      ticked: 0,
     }
   }
 
-  pub fn bus_connect(&mut self, bus: *mut Bus) {
-    self.bus = bus
-  }
-
+  /// Memory read taking one M-cycle. The rest of the hardware is ticked
+  /// first, so the read sees it as it is on the M-cycle of the access.
   pub fn read(&mut self, addr: u16) -> u8 {
-    unsafe { (*self.bus).read(addr) }
+    self.tick_m_cycle();
+    self.bus.read(addr)
   }
 
+  /// Memory write taking one M-cycle, see `read`.
   pub fn write(&mut self, addr: u16, data: u8) {
-    unsafe {
-      (*self.bus).write(addr, data);
-    }
+    self.tick_m_cycle();
+    self.bus.write(addr, data);
+  }
+
+  fn tick_m_cycle(&mut self) {
+    self.bus.tick(4);
+    self.ticked += 4;
   }
 
   pub fn debug(&self) {
@@ -114,13 +193,13 @@ impl Cpu {
     println!("{}", "=".repeat(40));
   }
 
-  pub fn view_memory_at(&self, memory: &[u8], address: usize, n: usize) {
+  pub fn view_memory_at(&self, address: usize, n: usize) {
     // Garantimos que não tentaremos acessar fora do limite de memória
-    let end_address = (address + n).min(memory.len());
+    let end_address = (address + n).min(0x10000);
 
-    let next_n_bytes: Vec<String> = memory[address..end_address]
-      .iter() // Itera sobre a slice da memória
-      .map(|&v| format!("0x{:02x}", v)) // Converte cada byte para o formato hexadecimal
+    let next_n_bytes: Vec<String> = (address..end_address)
+      .map(|addr| self.bus.read(addr as u16)) // Lê cada byte através do barramento
+      .map(|v| format!("0x{:02x}", v)) // Converte cada byte para o formato hexadecimal
       .collect(); // Coleta os resultados como uma `Vec<String>`
 
     println!(
@@ -160,16 +239,19 @@ impl Cpu {
 
   pub fn fetch(&mut self) -> u8 {
     let data = self.read(self.reg.pc);
-    self.reg.pc += 1;
+    if self.halt_bug {
+      self.halt_bug = false;
+    } else {
+      self.reg.pc = self.reg.pc.wrapping_add(1);
+    }
     data
   }
 
   pub fn fetch16(&mut self) -> u16 {
     let lo = self.read(self.reg.pc);
-    let hi = self.read(self.reg.pc + 1);
-    self.reg.pc += 2;
-    let data = ((hi as u16) << 8) | lo as u16;
-    data
+    let hi = self.read(self.reg.pc.wrapping_add(1));
+    self.reg.pc = self.reg.pc.wrapping_add(2);
+    ((hi as u16) << 8) | lo as u16
   }
 
   pub fn push(&mut self, data: u16) {
@@ -190,25 +272,328 @@ impl Cpu {
     let hi = self.read(self.reg.sp) as u16;
     self.reg.sp = self.reg.sp.wrapping_add(1);
 
-    let data = (hi << 8) | lo;
-    data
+    (hi << 8) | lo
+  }
+
+  /// Operand index encoded in the low 3 bits of most opcodes:
+  /// 0=B, 1=C, 2=D, 3=E, 4=H, 5=L, 6=(HL), 7=A
+  pub fn get_r8(&mut self, index: u8) -> u8 {
+    match index & 0b111 {
+      0 => self.reg.b,
+      1 => self.reg.c,
+      2 => self.reg.d,
+      3 => self.reg.e,
+      4 => self.reg.h,
+      5 => self.reg.l,
+      6 => self.read(self.reg.get_hl()),
+      _ => self.reg.a,
+    }
+  }
+
+  pub fn set_r8(&mut self, index: u8, data: u8) {
+    match index & 0b111 {
+      0 => self.reg.b = data,
+      1 => self.reg.c = data,
+      2 => self.reg.d = data,
+      3 => self.reg.e = data,
+      4 => self.reg.h = data,
+      5 => self.reg.l = data,
+      6 => self.write(self.reg.get_hl(), data),
+      _ => self.reg.a = data,
+    }
+  }
+
+  /// Condition encoded in bits 3-4 of JR/JP/CALL/RET: 0=NZ, 1=Z, 2=NC, 3=C
+  pub fn condition(&self, opcode: u8) -> bool {
+    match (opcode >> 3) & 0b11 {
+      0 => self.get_flag(Flags::Z) == 0,
+      1 => self.get_flag(Flags::Z) == 1,
+      2 => self.get_flag(Flags::C) == 0,
+      _ => self.get_flag(Flags::C) == 1,
+    }
+  }
+
+  pub fn inc(&mut self, value: u8) -> u8 {
+    let result = value.wrapping_add(1);
+
+    self.set_flag(Flags::Z, result == 0);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, (value & 0xF) == 0xF);
+    result
+  }
+
+  pub fn dec(&mut self, value: u8) -> u8 {
+    let result = value.wrapping_sub(1);
+
+    self.set_flag(Flags::Z, result == 0);
+    self.set_flag(Flags::N, true);
+    self.set_flag(Flags::H, (value & 0xF) == 0);
+    result
+  }
+
+  pub fn add(&mut self, value: u8, use_carry: bool) {
+    let a = self.reg.a;
+    let carry = if use_carry {
+      self.get_flag(Flags::C)
+    } else {
+      0
+    };
+    let result = a as u16 + value as u16 + carry as u16;
+
+    self.reg.a = result as u8;
+
+    self.set_flag(Flags::Z, self.reg.a == 0);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, (a & 0xF) + (value & 0xF) + carry > 0xF);
+    self.set_flag(Flags::C, result > 0xFF);
+  }
+
+  pub fn sub(&mut self, value: u8, use_carry: bool) -> u8 {
+    let a = self.reg.a;
+    let carry = if use_carry {
+      self.get_flag(Flags::C)
+    } else {
+      0
+    };
+    let result = a.wrapping_sub(value).wrapping_sub(carry);
+
+    self.set_flag(Flags::Z, result == 0);
+    self.set_flag(Flags::N, true);
+    self.set_flag(Flags::H, (a & 0xF) < (value & 0xF) + carry);
+    self.set_flag(Flags::C, (a as u16) < value as u16 + carry as u16);
+    result
+  }
+
+  pub fn and(&mut self, value: u8) {
+    self.reg.a &= value;
+
+    self.set_flag(Flags::Z, self.reg.a == 0);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, true);
+    self.set_flag(Flags::C, false);
+  }
+
+  pub fn xor(&mut self, value: u8) {
+    self.reg.a ^= value;
+
+    self.set_flag(Flags::Z, self.reg.a == 0);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, false);
+    self.set_flag(Flags::C, false);
+  }
+
+  pub fn or(&mut self, value: u8) {
+    self.reg.a |= value;
+
+    self.set_flag(Flags::Z, self.reg.a == 0);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, false);
+    self.set_flag(Flags::C, false);
+  }
+
+  /// ALU operation encoded in bits 3-5 of 0x80-0xBF and 0xC6-0xFE:
+  /// ADD, ADC, SUB, SBC, AND, XOR, OR, CP
+  pub fn alu(&mut self, opcode: u8, value: u8) {
+    match (opcode >> 3) & 0b111 {
+      0 => self.add(value, false),
+      1 => self.add(value, true),
+      2 => self.reg.a = self.sub(value, false),
+      3 => self.reg.a = self.sub(value, true),
+      4 => self.and(value),
+      5 => self.xor(value),
+      6 => self.or(value),
+      _ => {
+        self.sub(value, false);
+      }
+    }
+  }
+
+  pub fn add_hl(&mut self, value: u16) {
+    let hl = self.reg.get_hl();
+    let result = hl as u32 + value as u32;
+
+    self.reg.set_hl(result as u16);
+
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, (hl & 0xFFF) + (value & 0xFFF) > 0xFFF);
+    self.set_flag(Flags::C, result > 0xFFFF);
+  }
+
+  /// SP + e8, shared by ADD SP,e8 and LD HL,SP+e8. H and C come from the
+  /// unsigned addition of the low byte.
+  pub fn add_sp_e8(&mut self) -> u16 {
+    let sp = self.reg.sp;
+    let e = self.fetch();
+
+    self.set_flag(Flags::Z, false);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, (sp & 0xF) + (e as u16 & 0xF) > 0xF);
+    self.set_flag(Flags::C, (sp & 0xFF) + e as u16 > 0xFF);
+
+    sp.wrapping_add(e as i8 as u16)
+  }
+
+  pub fn daa(&mut self) {
+    let mut a = self.reg.a;
+    let mut adjust = 0;
+    let mut carry = self.get_flag(Flags::C) == 1;
+
+    if self.get_flag(Flags::N) == 0 {
+      if carry || a > 0x99 {
+        adjust |= 0x60;
+        carry = true;
+      }
+      if self.get_flag(Flags::H) == 1 || (a & 0xF) > 0x9 {
+        adjust |= 0x06;
+      }
+      a = a.wrapping_add(adjust);
+    } else {
+      if carry {
+        adjust |= 0x60;
+      }
+      if self.get_flag(Flags::H) == 1 {
+        adjust |= 0x06;
+      }
+      a = a.wrapping_sub(adjust);
+    }
+
+    self.reg.a = a;
+
+    self.set_flag(Flags::Z, a == 0);
+    self.set_flag(Flags::H, false);
+    self.set_flag(Flags::C, carry);
+  }
+
+  pub fn jr(&mut self, condition: bool) {
+    let data = self.fetch() as i8;
+    if condition {
+      self.reg.pc = self.reg.pc.wrapping_add(data as u16);
+      self.set_cycles(12);
+    } else {
+      self.set_cycles(8);
+    }
+  }
+
+  pub fn jp(&mut self, condition: bool) {
+    let addr = self.fetch16();
+    if condition {
+      self.reg.pc = addr;
+      self.set_cycles(16);
+    } else {
+      self.set_cycles(12);
+    }
+  }
+
+  pub fn call(&mut self, condition: bool) {
+    let addr = self.fetch16();
+    if condition {
+      self.push(self.reg.pc);
+      self.reg.pc = addr;
+      self.set_cycles(24);
+    } else {
+      self.set_cycles(12);
+    }
+  }
+
+  pub fn ret(&mut self, condition: bool) {
+    if condition {
+      self.reg.pc = self.pop();
+      self.set_cycles(20);
+    } else {
+      self.set_cycles(8);
+    }
+  }
+
+  pub fn rst(&mut self, vector: u16) {
+    self.push(self.reg.pc);
+    self.reg.pc = vector;
+    self.set_cycles(16);
+  }
+
+  /// Shift/rotate operation encoded in bits 3-5 of CB 0x00-0x3F:
+  /// RLC, RRC, RL, RR, SLA, SRA, SWAP, SRL
+  pub fn shift(&mut self, opcode: u8, value: u8) -> u8 {
+    let carry = self.get_flag(Flags::C);
+
+    let (result, carry_out) = match (opcode >> 3) & 0b111 {
+      0 => (value.rotate_left(1), value >> 7),
+      1 => (value.rotate_right(1), value & 0b1),
+      2 => ((value << 1) | carry, value >> 7),
+      3 => ((value >> 1) | (carry << 7), value & 0b1),
+      4 => (value << 1, value >> 7),
+      5 => ((value >> 1) | (value & 0x80), value & 0b1),
+      6 => (value.rotate_left(4), 0),
+      _ => (value >> 1, value & 0b1),
+    };
+
+    self.set_flag(Flags::Z, result == 0);
+    self.set_flag(Flags::N, false);
+    self.set_flag(Flags::H, false);
+    self.set_flag(Flags::C, carry_out == 1);
+    result
   }
 
-  pub fn decode(&mut self, instruction: u8) -> Result<(), String> {
+  pub fn decode_cb(&mut self, instruction: u8) {
+    let index = instruction & 0b111;
+    let bit = (instruction >> 3) & 0b111;
+    let data = self.get_r8(index);
+
+    match instruction {
+      // RLC, RRC, RL, RR, SLA, SRA, SWAP, SRL r
+      0x00..=0x3F => {
+        let result = self.shift(instruction, data);
+        self.set_r8(index, result);
+      }
+      // BIT b, r
+      0x40..=0x7F => {
+        self.set_flag(Flags::Z, (data >> bit) & 0b1 == 0);
+        self.set_flag(Flags::N, false);
+        self.set_flag(Flags::H, true);
+      }
+      // RES b, r
+      0x80..=0xBF => {
+        self.set_r8(index, data & !(1 << bit));
+      }
+      // SET b, r
+      0xC0..=0xFF => {
+        self.set_r8(index, data | (1 << bit));
+      }
+    }
+
+    let cycles = match (index, instruction) {
+      (6, 0x40..=0x7F) => 12,
+      (6, _) => 16,
+      _ => 8,
+    };
+    self.set_cycles(cycles);
+  }
+
+  pub fn decode(&mut self, instruction: u8) -> Result<(), CpuError> {
     match instruction {
       0x00 => {
         self.set_cycles(4);
       }
+      0x01 => {
+        let data = self.fetch16();
+        self.reg.set_bc(data);
+        self.set_cycles(12);
+      }
+      0x02 => {
+        let addr = self.reg.get_bc();
+        self.write(addr, self.reg.a);
+        self.set_cycles(8);
+      }
+      0x03 => {
+        let bc = self.reg.get_bc();
+        self.reg.set_bc(bc.wrapping_add(1));
+        self.set_cycles(8);
+      }
+      0x04 => {
+        self.reg.b = self.inc(self.reg.b);
+        self.set_cycles(4);
+      }
       0x05 => {
-        let before = self.reg.b;
-        let result = self.reg.b.wrapping_sub(1);
-        self.reg.b = result;
-
-        let hc = (before & 0xF) < 1;
-
-        self.set_flag(Flags::Z, result == 0);
-        self.set_flag(Flags::N, true);
-        self.set_flag(Flags::H, hc);
+        self.reg.b = self.dec(self.reg.b);
         self.set_cycles(4);
       }
       0x06 => {
@@ -216,15 +601,44 @@ impl Cpu {
         self.reg.b = data;
         self.set_cycles(8);
       }
-      0x0C => {
-        let before = self.reg.c;
-        let result = self.reg.c.wrapping_add(1);
-        self.reg.c = result; 
-        let hc = (before & 0xF) + (1 & 0xF);
+      0x07 => {
+        let bit_7 = (self.reg.a >> 7) & 0b1;
 
-        self.set_flag(Flags::Z, result == 0);
+        self.reg.a = self.reg.a.rotate_left(1);
+
+        self.set_flag(Flags::Z, false);
         self.set_flag(Flags::N, false);
-        self.set_flag(Flags::H, hc > 0xF);
+        self.set_flag(Flags::H, false);
+        self.set_flag(Flags::C, bit_7 == 1);
+        self.set_cycles(4);
+      }
+      0x08 => {
+        let addr = self.fetch16();
+        let sp = self.reg.sp;
+        self.write(addr, (sp & 0xFF) as u8);
+        self.write(addr.wrapping_add(1), (sp >> 8) as u8);
+        self.set_cycles(20);
+      }
+      0x09 => {
+        self.add_hl(self.reg.get_bc());
+        self.set_cycles(8);
+      }
+      0x0A => {
+        let addr = self.reg.get_bc();
+        self.reg.a = self.read(addr);
+        self.set_cycles(8);
+      }
+      0x0B => {
+        let bc = self.reg.get_bc();
+        self.reg.set_bc(bc.wrapping_sub(1));
+        self.set_cycles(8);
+      }
+      0x0C => {
+        self.reg.c = self.inc(self.reg.c);
+        self.set_cycles(4);
+      }
+      0x0D => {
+        self.reg.c = self.dec(self.reg.c);
         self.set_cycles(4);
       }
       0x0E => {
@@ -232,14 +646,52 @@ impl Cpu {
         self.reg.c = data;
         self.set_cycles(8);
       }
+      0x0F => {
+        let bit_0 = self.reg.a & 0b1;
+
+        self.reg.a = self.reg.a.rotate_right(1);
+
+        self.set_flag(Flags::Z, false);
+        self.set_flag(Flags::N, false);
+        self.set_flag(Flags::H, false);
+        self.set_flag(Flags::C, bit_0 == 1);
+        self.set_cycles(4);
+      }
+      0x10 => {
+        // STOP is encoded as two bytes, the second one is skipped without
+        // being read.
+        self.reg.pc = self.reg.pc.wrapping_add(1);
+        self.stopped = true;
+        // Entering STOP mode resets the divider.
+        self.bus.write(DIV_ADDR, 0);
+        self.set_cycles(4);
+      }
       0x11 => {
         let data = self.fetch16();
         self.reg.set_de(data);
         self.set_cycles(12);
       }
+      0x12 => {
+        let addr = self.reg.get_de();
+        self.write(addr, self.reg.a);
+        self.set_cycles(8);
+      }
       0x13 => {
         let de = self.reg.get_de();
-        self.reg.set_de(de + 1);
+        self.reg.set_de(de.wrapping_add(1));
+        self.set_cycles(8);
+      }
+      0x14 => {
+        self.reg.d = self.inc(self.reg.d);
+        self.set_cycles(4);
+      }
+      0x15 => {
+        self.reg.d = self.dec(self.reg.d);
+        self.set_cycles(4);
+      }
+      0x16 => {
+        let data = self.fetch();
+        self.reg.d = data;
         self.set_cycles(8);
       }
       0x17 => {
@@ -255,20 +707,49 @@ impl Cpu {
 
         self.set_cycles(4);
       }
+      0x18 => {
+        self.jr(true);
+      }
+      0x19 => {
+        self.add_hl(self.reg.get_de());
+        self.set_cycles(8);
+      }
       0x1A => {
         let addr = self.reg.get_de();
         let data = self.read(addr);
         self.reg.a = data;
         self.set_cycles(8);
       }
-      0x20 => {
-        let data = self.fetch() as i8;
-        if self.get_flag(Flags::Z) == 1 {
-          self.reg.pc = self.reg.pc.wrapping_add(data as u16);
-          self.set_cycles(12);
-        }
+      0x1B => {
+        let de = self.reg.get_de();
+        self.reg.set_de(de.wrapping_sub(1));
+        self.set_cycles(8);
+      }
+      0x1C => {
+        self.reg.e = self.inc(self.reg.e);
+        self.set_cycles(4);
+      }
+      0x1D => {
+        self.reg.e = self.dec(self.reg.e);
+        self.set_cycles(4);
+      }
+      0x1E => {
+        let data = self.fetch();
+        self.reg.e = data;
         self.set_cycles(8);
       }
+      0x1F => {
+        let carry = self.get_flag(Flags::C);
+        let bit_0 = self.reg.a & 0b1;
+
+        self.reg.a = (self.reg.a >> 1) | (carry << 7);
+
+        self.set_flag(Flags::Z, false);
+        self.set_flag(Flags::N, false);
+        self.set_flag(Flags::H, false);
+        self.set_flag(Flags::C, bit_0 == 1);
+        self.set_cycles(4);
+      }
       0x21 => {
         let data = self.fetch16();
         self.reg.set_hl(data);
@@ -278,14 +759,70 @@ impl Cpu {
         let data = self.reg.a;
         let addr = self.reg.get_hl();
         self.write(addr, data);
-        self.reg.set_hl(addr + 1);
+        self.reg.set_hl(addr.wrapping_add(1));
         self.set_cycles(8);
       }
       0x23 => {
         let hl = self.reg.get_hl();
-        self.reg.set_hl(hl + 1);
+        self.reg.set_hl(hl.wrapping_add(1));
+        self.set_cycles(8);
+      }
+      0x24 => {
+        self.reg.h = self.inc(self.reg.h);
+        self.set_cycles(4);
+      }
+      0x25 => {
+        self.reg.h = self.dec(self.reg.h);
+        self.set_cycles(4);
+      }
+      0x26 => {
+        let data = self.fetch();
+        self.reg.h = data;
+        self.set_cycles(8);
+      }
+      0x27 => {
+        self.daa();
+        self.set_cycles(4);
+      }
+      0x20 | 0x28 | 0x30 | 0x38 => {
+        let condition = self.condition(instruction);
+        self.jr(condition);
+      }
+      0x29 => {
+        self.add_hl(self.reg.get_hl());
+        self.set_cycles(8);
+      }
+      0x2A => {
+        let addr = self.reg.get_hl();
+        self.reg.a = self.read(addr);
+        self.reg.set_hl(addr.wrapping_add(1));
+        self.set_cycles(8);
+      }
+      0x2B => {
+        let hl = self.reg.get_hl();
+        self.reg.set_hl(hl.wrapping_sub(1));
         self.set_cycles(8);
       }
+      0x2C => {
+        self.reg.l = self.inc(self.reg.l);
+        self.set_cycles(4);
+      }
+      0x2D => {
+        self.reg.l = self.dec(self.reg.l);
+        self.set_cycles(4);
+      }
+      0x2E => {
+        let data = self.fetch();
+        self.reg.l = data;
+        self.set_cycles(8);
+      }
+      0x2F => {
+        self.reg.a = !self.reg.a;
+
+        self.set_flag(Flags::N, true);
+        self.set_flag(Flags::H, true);
+        self.set_cycles(4);
+      }
       0x31 => {
         let data = self.fetch16();
         self.reg.sp = data;
@@ -296,84 +833,137 @@ impl Cpu {
         let data = self.reg.a;
         self.write(addr, data);
 
-        self.reg.set_hl(addr - 1);
+        self.reg.set_hl(addr.wrapping_sub(1));
         self.set_cycles(8);
       }
-      0x3E => {
-        let data = self.fetch();
-        self.reg.a = data;
+      0x33 => {
+        self.reg.sp = self.reg.sp.wrapping_add(1);
         self.set_cycles(8);
       }
-      0x4F => {
-        let data = self.reg.a;
-        self.reg.c = data;
-        self.set_cycles(4);
+      0x34 => {
+        let addr = self.reg.get_hl();
+        let data = self.read(addr);
+        let result = self.inc(data);
+        self.write(addr, result);
+        self.set_cycles(12);
       }
-      0x77 => {
-        let data = self.reg.a;
+      0x35 => {
+        let addr = self.reg.get_hl();
+        let data = self.read(addr);
+        let result = self.dec(data);
+        self.write(addr, result);
+        self.set_cycles(12);
+      }
+      0x36 => {
+        let data = self.fetch();
         let addr = self.reg.get_hl();
         self.write(addr, data);
+        self.set_cycles(12);
+      }
+      0x37 => {
+        self.set_flag(Flags::N, false);
+        self.set_flag(Flags::H, false);
+        self.set_flag(Flags::C, true);
+        self.set_cycles(4);
+      }
+      0x39 => {
+        self.add_hl(self.reg.sp);
         self.set_cycles(8);
       }
-      0x7B => {
-        let e = self.reg.e;
-        self.reg.a = e;
+      0x3A => {
+        let addr = self.reg.get_hl();
+        self.reg.a = self.read(addr);
+        self.reg.set_hl(addr.wrapping_sub(1));
+        self.set_cycles(8);
+      }
+      0x3B => {
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+        self.set_cycles(8);
+      }
+      0x3C => {
+        self.reg.a = self.inc(self.reg.a);
         self.set_cycles(4);
       }
-      0xAF => {
-        let result = self.reg.a ^ self.reg.a;
-        self.reg.a = result;
+      0x3D => {
+        self.reg.a = self.dec(self.reg.a);
+        self.set_cycles(4);
+      }
+      0x3E => {
+        let data = self.fetch();
+        self.reg.a = data;
+        self.set_cycles(8);
+      }
+      0x3F => {
+        let carry = self.get_flag(Flags::C);
 
-        self.set_flag(Flags::Z, result == 0);
         self.set_flag(Flags::N, false);
         self.set_flag(Flags::H, false);
-        self.set_flag(Flags::C, false);
+        self.set_flag(Flags::C, carry == 0);
         self.set_cycles(4);
       }
+      0x76 => {
+        if !self.ime && self.pending_interrupts() != 0 {
+          self.halt_bug = true;
+        } else {
+          self.halted = true;
+        }
+        self.set_cycles(4);
+      }
+      // LD r, r'
+      0x40..=0x7F => {
+        let data = self.get_r8(instruction);
+        self.set_r8(instruction >> 3, data);
+
+        let uses_hl = (instruction & 0b111) == 6 || ((instruction >> 3) & 0b111) == 6;
+        self.set_cycles(if uses_hl { 8 } else { 4 });
+      }
+      // ADD, ADC, SUB, SBC, AND, XOR, OR, CP with A, r
+      0x80..=0xBF => {
+        let data = self.get_r8(instruction);
+        self.alu(instruction, data);
+        self.set_cycles(if (instruction & 0b111) == 6 { 8 } else { 4 });
+      }
+      0xC0 | 0xC8 | 0xD0 | 0xD8 => {
+        let condition = self.condition(instruction);
+        self.ret(condition);
+      }
       0xC1 => {
         let data = self.pop();
         self.reg.set_bc(data);
         self.set_cycles(12);
       }
+      0xC2 | 0xCA | 0xD2 | 0xDA => {
+        let condition = self.condition(instruction);
+        self.jp(condition);
+      }
+      0xC3 => {
+        self.jp(true);
+      }
+      0xC4 | 0xCC | 0xD4 | 0xDC => {
+        let condition = self.condition(instruction);
+        self.call(condition);
+      }
       0xC5 => {
         let data = self.reg.get_bc();
         self.push(data);
         self.set_cycles(16);
       }
+      0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE => {
+        let data = self.fetch();
+        self.alu(instruction, data);
+        self.set_cycles(8);
+      }
+      0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
+        self.rst((instruction & 0x38) as u16);
+      }
       0xC9 => {
         let addr = self.pop();
         self.reg.pc = addr;
         self.set_cycles(16);
       }
       0xCB => {
-        let cb_intruction = self.fetch();
-
-        match cb_intruction {
-          0x11 => {
-            let carry = self.get_flag(Flags::C);
-            let bit_7 = (self.reg.c >> 7) & 0b1;
-
-            self.reg.c = (self.reg.c << 1) | carry;
-
-            self.set_flag(Flags::Z, self.reg.c == 0);
-            self.set_flag(Flags::N, false);
-            self.set_flag(Flags::H, false);
-            self.set_flag(Flags::C, bit_7 == 1);
-
-            self.set_cycles(8);
-
-          }
-          0x7C => {
-            let bit_7_h = (self.reg.h >> 7) & 0b1;
-
-            self.set_flag(Flags::Z, bit_7_h == 1);
-            self.set_flag(Flags::N, false);
-            self.set_flag(Flags::H, true);
-            self.set_cycles(8);
-          }
-          _ => return Err(format!("Unknow CB instruction. OPCODE: {:02X}", cb_intruction)),
-        }
-        log::error!("PREFIX CB: OPCODE: {:02X}", cb_intruction);
+        let cb_instruction = self.fetch();
+        self.decode_cb(cb_instruction);
       }
       0xCD => {
         let nn = self.fetch16();
@@ -381,48 +971,210 @@ impl Cpu {
         self.reg.pc = nn;
         self.set_cycles(24);
       }
+      0xD1 => {
+        let data = self.pop();
+        self.reg.set_de(data);
+        self.set_cycles(12);
+      }
+      0xD5 => {
+        let data = self.reg.get_de();
+        self.push(data);
+        self.set_cycles(16);
+      }
+      0xD9 => {
+        self.reg.pc = self.pop();
+        self.ime = true;
+        self.ime_scheduled = false;
+        self.set_cycles(16);
+      }
       0xE0 => {
-        let hi = (0xFF << 8) as u16;
+        let hi = 0xFF00;
         let lo = self.fetch() as u16;
         let addr = hi | lo;
         let data = self.reg.a;
         self.write(addr, data);
         self.set_cycles(12);
       }
+      0xE1 => {
+        let data = self.pop();
+        self.reg.set_hl(data);
+        self.set_cycles(12);
+      }
       0xE2 => {
-        let hi = (0xFF << 8) as u16;
+        let hi = 0xFF00;
         let lo = self.reg.c as u16;
         let addr = hi | lo;
         self.write(addr, self.reg.a);
         self.set_cycles(8);
       }
-      0xFE => {
-        let before = self.reg.a;
-        let n = self.fetch();
-        let a = self.reg.a;
-        let result = a.wrapping_sub(n);
-        let hc = (before & 0xF) < 1;
-        let bit_7 = (result >> 7) & 0b1;
+      0xE5 => {
+        let data = self.reg.get_hl();
+        self.push(data);
+        self.set_cycles(16);
+      }
+      0xE8 => {
+        self.reg.sp = self.add_sp_e8();
+        self.set_cycles(16);
+      }
+      0xE9 => {
+        self.reg.pc = self.reg.get_hl();
+        self.set_cycles(4);
+      }
+      0xEA => {
+        let addr = self.fetch16();
+        self.write(addr, self.reg.a);
+        self.set_cycles(16);
+      }
+      0xF0 => {
+        let hi = 0xFF00;
+        let lo = self.fetch() as u16;
+        self.reg.a = self.read(hi | lo);
+        self.set_cycles(12);
+      }
+      0xF1 => {
+        let data = self.pop();
+        self.reg.set_af(data);
+        self.set_cycles(12);
+      }
+      0xF2 => {
+        let hi = 0xFF00;
+        let lo = self.reg.c as u16;
+        self.reg.a = self.read(hi | lo);
+        self.set_cycles(8);
+      }
+      0xF3 => {
+        self.ime = false;
+        self.ime_scheduled = false;
+        self.set_cycles(4);
+      }
+      0xF5 => {
+        let data = self.reg.get_af();
+        self.push(data);
+        self.set_cycles(16);
+      }
+      0xF8 => {
+        let data = self.add_sp_e8();
+        self.reg.set_hl(data);
+        self.set_cycles(12);
+      }
+      0xF9 => {
+        self.reg.sp = self.reg.get_hl();
+        self.set_cycles(8);
+      }
+      0xFA => {
+        let addr = self.fetch16();
+        self.reg.a = self.read(addr);
+        self.set_cycles(16);
+      }
+      0xFB => {
+        self.ime_scheduled = !self.ime;
+        self.set_cycles(4);
+      }
+      0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
+        self.locked = true;
+        self.set_cycles(4);
+        return Err(CpuError::IllegalOpcode {
+          opcode: instruction,
+          pc: self.instruction_pc,
+        });
+      }
+    }
 
-        self.set_flag(Flags::Z, result == 0);
-        self.set_flag(Flags::N, true);
-        self.set_flag(Flags::H, hc);
-        self.set_flag(Flags::C, bit_7 == 1);
+    Ok(())
+  }
 
+  pub fn pending_interrupts(&mut self) -> u8 {
+    self.bus.read(IE_ADDR) & self.bus.read(IF_ADDR) & 0x1F
+  }
+
+  /// Pushes PC and jumps to the vector of the highest priority pending
+  /// interrupt. Takes 5 M-cycles. If the push overwrites IE and cancels every
+  /// pending interrupt, PC ends up at 0x0000.
+  pub fn dispatch_interrupt(&mut self) {
+    self.ime = false;
+    self.halted = false;
+
+    // After EI; HALT with an interrupt already pending, the handler returns
+    // to the HALT, which then runs again.
+    let pc = if self.halt_bug {
+      self.halt_bug = false;
+      self.reg.pc.wrapping_sub(1)
+    } else {
+      self.reg.pc
+    };
+    self.reg.sp = self.reg.sp.wrapping_sub(1);
+    self.write(self.reg.sp, (pc >> 8) as u8);
+
+    let pending = self.pending_interrupts();
+
+    self.reg.sp = self.reg.sp.wrapping_sub(1);
+    self.write(self.reg.sp, (pc & 0xFF) as u8);
+
+    match Interrupt::highest(pending) {
+      Some(interrupt) => {
+        let flag = self.bus.read(IF_ADDR);
+        self.bus.write(IF_ADDR, flag & !interrupt.bit());
+        self.reg.pc = interrupt.vector();
       }
-      _ => return Err(format!("Unknow instruction. OPCODE: {:02X}", instruction)),
+      None => self.reg.pc = 0x0000,
     }
 
-    Ok(())
+    self.set_cycles(20);
   }
 
-  pub fn step(&mut self) -> Result<(), String> {
-    // self.debug();
+  /// Runs one instruction, or one interrupt dispatch, and advances the rest
+  /// of the hardware by the cycles it took. Memory accesses tick it as they
+  /// happen, the internal M-cycles without an access are ticked at the end,
+  /// even those that come before an access on hardware (like the delay at
+  /// the start of PUSH).
+  pub fn step(&mut self) -> Result<(), CpuError> {
+    self.ticked = 0;
+    let result = self.execute();
+    self.bus.tick(self.cycles - self.ticked);
+    result
+  }
+
+  fn execute(&mut self) -> Result<(), CpuError> {
+    // The rest of the hardware keeps running while the CPU is locked up.
+    if self.locked {
+      self.set_cycles(4);
+      return Ok(());
+    }
+
+    let pending = self.pending_interrupts();
+
+    if self.stopped {
+      if pending & Interrupt::Joypad.bit() == 0 {
+        self.set_cycles(4);
+        return Ok(());
+      }
+      self.stopped = false;
+    }
+
+    if self.halted {
+      if pending == 0 {
+        self.set_cycles(4);
+        return Ok(());
+      }
+      self.halted = false;
+    }
 
+    if self.ime && pending != 0 {
+      self.dispatch_interrupt();
+      return Ok(());
+    }
+
+    let enable_ime = self.ime_scheduled;
+
+    self.instruction_pc = self.reg.pc;
     let instruction = self.fetch();
-    match self.decode(instruction) {
-      Err(e) => return Err(format!("ERROR: {}", e)),
-      Ok(_) => return Ok(()),
+    let result = self.decode(instruction);
+
+    if enable_ime && self.ime_scheduled {
+      self.ime_scheduled = false;
+      self.ime = true;
     }
+
+    result
   }
 }
@@ -0,0 +1,39 @@
+pub const DMA_ADDR: u16 = 0xFF46;
+
+/// Bytes copied into OAM by one transfer, one per M-cycle.
+pub const DMA_LENGTH: u8 = 0xA0;
+
+/// OAM DMA. Writing XX to FF46 copies XX00-XX9F into OAM, one byte per
+/// M-cycle. The CPU can't use OAM while the transfer runs.
+#[derive(Debug, Default)]
+pub struct Dma {
+  /// Last value written to FF46, which reads back.
+  pub source: u8,
+  // Next byte to copy, None when no transfer is running.
+  index: Option<u8>,
+}
+
+impl Dma {
+  pub fn new() -> Dma {
+    Dma::default()
+  }
+
+  /// A transfer is running and OAM is off limits to the CPU.
+  pub fn active(&self) -> bool {
+    self.index.is_some()
+  }
+
+  /// Starts a transfer from `source`00, restarting any running one.
+  pub fn start(&mut self, source: u8) {
+    self.source = source;
+    self.index = Some(0);
+  }
+
+  /// Advances the transfer by one M-cycle and returns the address to copy
+  /// from and the OAM offset to copy to.
+  pub fn step(&mut self) -> Option<(u16, usize)> {
+    let index = self.index?;
+    self.index = (index + 1 < DMA_LENGTH).then_some(index + 1);
+    Some((u16::from_be_bytes([self.source, index]), index as usize))
+  }
+}
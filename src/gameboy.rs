@@ -0,0 +1,102 @@
+use crate::boot::{self, BootRom};
+use crate::bus::Bus;
+use crate::cartridge::Cartridge;
+use crate::cpu::{Cpu, CpuError};
+use crate::model::Model;
+
+/// T-cycles per frame: 154 lines of 456 dots.
+pub const CYCLES_PER_FRAME: usize = 70224;
+
+/// The whole console. The CPU owns the bus, which owns the cartridge, the
+/// timer, the PPU, the APU and the rest of the memory mapped hardware, so
+/// everything runs in lock step from `Cpu::step`.
+#[derive(Debug)]
+pub struct GameBoy {
+  pub cpu: Cpu,
+  // T-cycles run since the start of the current frame.
+  frame_cycles: usize,
+}
+
+impl GameBoy {
+  /// Starts the boot ROM at 0x0000 if there is one, otherwise starts at the
+  /// cartridge entry point as if the boot ROM of `model` had run.
+  pub fn new(cartridge: Cartridge, model: Model, boot_rom: Option<BootRom>) -> GameBoy {
+    let mut cpu = Cpu::new();
+    cpu.bus.model = model;
+    cpu.bus.insert_cartridge(cartridge);
+
+    match boot_rom {
+      Some(boot_rom) => cpu.bus.boot_rom = Some(boot_rom),
+      None => boot::skip_boot(&mut cpu),
+    }
+
+    GameBoy {
+      cpu,
+      frame_cycles: 0,
+    }
+  }
+
+  /// The memory map and the hardware behind it.
+  pub fn bus(&self) -> &Bus {
+    &self.cpu.bus
+  }
+
+  pub fn bus_mut(&mut self) -> &mut Bus {
+    &mut self.cpu.bus
+  }
+
+  /// The inserted cartridge, e.g. to flush its save.
+  pub fn cartridge(&self) -> &Cartridge {
+    &self.cpu.bus.cartridge
+  }
+
+  pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+    &mut self.cpu.bus.cartridge
+  }
+
+  /// One shade (0-3) per pixel, row by row, see `Ppu`.
+  pub fn framebuffer(&self) -> &[u8] {
+    self.cpu.bus.ppu.framebuffer()
+  }
+
+  /// Runs one instruction (or interrupt dispatch) and returns the T-cycles
+  /// it took.
+  pub fn step_instruction(&mut self) -> Result<usize, CpuError> {
+    self.cpu.step()?;
+    self.frame_cycles += self.cpu.cycles;
+    Ok(self.cpu.cycles)
+  }
+
+  /// Runs whole instructions until at least `cycles` T-cycles have passed
+  /// and returns how many did.
+  pub fn run_cycles(&mut self, cycles: usize) -> Result<usize, CpuError> {
+    let mut elapsed = 0;
+    while elapsed < cycles {
+      elapsed += self.step_instruction()?;
+    }
+    Ok(elapsed)
+  }
+
+  /// Runs until the PPU enters VBlank, so the framebuffer holds one whole
+  /// frame. While the LCD is off, frames are `CYCLES_PER_FRAME` long instead
+  /// and instructions that cross the frame boundary count towards the next
+  /// frame.
+  pub fn run_frame(&mut self) -> Result<(), CpuError> {
+    // A VBlank reached through `step_instruction` ended an earlier frame.
+    self.cpu.bus.ppu.take_frame_ready();
+
+    loop {
+      self.step_instruction()?;
+
+      let ppu = &mut self.cpu.bus.ppu;
+      if ppu.take_frame_ready() {
+        self.frame_cycles = 0;
+        return Ok(());
+      }
+      if !ppu.lcd_enabled() && self.frame_cycles >= CYCLES_PER_FRAME {
+        self.frame_cycles -= CYCLES_PER_FRAME;
+        return Ok(());
+      }
+    }
+  }
+}
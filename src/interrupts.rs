@@ -0,0 +1,76 @@
+pub const IF_ADDR: u16 = 0xFF0F;
+pub const IE_ADDR: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+  VBlank,
+  LcdStat,
+  Timer,
+  Serial,
+  Joypad,
+}
+
+impl Interrupt {
+  /// Ordered by priority, highest first.
+  pub const ALL: [Interrupt; 5] = [
+    Interrupt::VBlank,
+    Interrupt::LcdStat,
+    Interrupt::Timer,
+    Interrupt::Serial,
+    Interrupt::Joypad,
+  ];
+
+  /// Bit of the interrupt in IE and IF.
+  pub fn bit(self) -> u8 {
+    match self {
+      Interrupt::VBlank => 1 << 0,
+      Interrupt::LcdStat => 1 << 1,
+      Interrupt::Timer => 1 << 2,
+      Interrupt::Serial => 1 << 3,
+      Interrupt::Joypad => 1 << 4,
+    }
+  }
+
+  /// Address the CPU jumps to when dispatching the interrupt.
+  pub fn vector(self) -> u16 {
+    match self {
+      Interrupt::VBlank => 0x40,
+      Interrupt::LcdStat => 0x48,
+      Interrupt::Timer => 0x50,
+      Interrupt::Serial => 0x58,
+      Interrupt::Joypad => 0x60,
+    }
+  }
+
+  /// Highest priority interrupt set in `pending`.
+  pub fn highest(pending: u8) -> Option<Interrupt> {
+    Interrupt::ALL.into_iter().find(|i| pending & i.bit() != 0)
+  }
+}
+
+/// IE (0xFFFF) and IF (0xFF0F) registers.
+#[derive(Debug, Default)]
+pub struct InterruptController {
+  pub enable: u8,
+  pub flag: u8,
+}
+
+impl InterruptController {
+  pub fn new() -> InterruptController {
+    InterruptController::default()
+  }
+
+  /// Sets the bit of `interrupt` in IF.
+  pub fn request(&mut self, interrupt: Interrupt) {
+    self.flag |= interrupt.bit();
+  }
+
+  pub fn read_flag(&self) -> u8 {
+    // Upper 3 bits of IF are unused and read as 1.
+    self.flag | 0xE0
+  }
+
+  pub fn write_flag(&mut self, data: u8) {
+    self.flag = data & 0x1F;
+  }
+}
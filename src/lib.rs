@@ -0,0 +1,52 @@
+//! Game Boy emulation core.
+//!
+//! The crate has no frontend dependencies: it runs the hardware and exposes
+//! its state, and a frontend (like the SDL debugger in `main.rs`, built with
+//! the default `sdl` feature) draws it and feeds it input.
+//!
+//! - [`gameboy`]: the whole console, driven one instruction, a number of
+//!   cycles or a frame at a time.
+//! - [`cpu`]: the SM83 core, generic over the [`memory::MemoryInterface`] it
+//!   runs on.
+//! - [`bus`]: the memory map and the hardware behind it.
+//! - [`cartridge`]: ROM loading (archives, patches), headers, memory bank
+//!   controllers and battery saves.
+//! - [`boot`], [`model`]: boot ROM handling and the post-boot state of each
+//!   hardware model.
+//! - [`interrupts`], [`timer`]: IE/IF and DIV/TIMA/TMA/TAC.
+//! - [`ppu`], [`dma`]: VRAM, OAM, the LCD registers, the 160x144
+//!   framebuffer and OAM DMA.
+//! - [`apu`]: the sound registers, stored but not played yet.
+//!
+//! ```no_run
+//! use gameboy_emulator::{Cartridge, GameBoy, Model};
+//!
+//! let cartridge = Cartridge::from_file("game.gb")?;
+//! let model = Model::detect(&cartridge.header);
+//! let mut gameboy = GameBoy::new(cartridge, model, None);
+//!
+//! gameboy.run_frame()?;
+//! println!("PC: {:04X}", gameboy.cpu.reg.pc);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod apu;
+pub mod boot;
+pub mod bus;
+pub mod cartridge;
+pub mod cpu;
+pub mod dma;
+pub mod gameboy;
+pub mod interrupts;
+pub mod memory;
+pub mod model;
+pub mod ppu;
+pub mod timer;
+pub mod utils;
+
+#[cfg(test)]
+mod tests;
+
+pub use cartridge::Cartridge;
+pub use gameboy::GameBoy;
+pub use model::Model;
@@ -1,67 +1,156 @@
 use std::env;
-use std::fs::File;
-use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
 
-mod bus;
-mod cpu;
-mod utils;
 mod win_sdl;
 
-use utils::fps_counter::FpsCounter;
-use utils::frame_counter::FrameCounter;
+use win_sdl::fps_counter::FpsCounter;
+use win_sdl::frame_counter::FrameCounter;
 
-use bus::Bus;
-use cpu::Cpu;
+use gameboy_emulator::boot::BootRom;
+use gameboy_emulator::cartridge::{self, Cartridge, CartridgeEvent, LoadOptions};
+use gameboy_emulator::cpu::CpuError;
+use gameboy_emulator::gameboy::GameBoy;
+use gameboy_emulator::model::Model;
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use win_sdl::WinSDL;
 
-use log;
+// How often battery backed RAM is written to disk while running.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+struct Args {
+  rom_path: String,
+  patches: Vec<PathBuf>,
+  // File to load from a zip archive.
+  entry: Option<String>,
+  boot_rom: Option<PathBuf>,
+  skip_boot: bool,
+  // Detected from the cartridge when not given.
+  model: Option<Model>,
+}
 
-fn main() {
-  if env::args().len() < 2 {
-    log::error!("Error: rom not found.");
-    log::info!("Usage: cargo run <ROM_PATH>");
+impl Args {
+  fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut rom_path = None;
+    let mut patches = Vec::new();
+    let mut entry = None;
+    let mut boot_rom = None;
+    let mut skip_boot = false;
+    let mut model = None;
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--entry" => entry = Some(args.next().ok_or("--entry needs a file name")?),
+        "--boot-rom" => {
+          boot_rom = Some(PathBuf::from(args.next().ok_or("--boot-rom needs a path")?))
+        }
+        "--skip-boot" => skip_boot = true,
+        "--model" => model = Some(args.next().ok_or("--model needs a name")?.parse()?),
+        _ if rom_path.is_none() => rom_path = Some(arg),
+        _ => patches.push(PathBuf::from(arg)),
+      }
+    }
+
+    Ok(Args {
+      rom_path: rom_path.ok_or("rom not found.")?,
+      patches,
+      entry,
+      boot_rom,
+      skip_boot,
+      model,
+    })
   }
+}
 
-  let rom_path = env::args().nth(1).unwrap();
-  let mut rom = File::open(&rom_path).unwrap();
-  let mut rom_buffer: Vec<u8> = Vec::new();
-  rom.read_to_end(&mut rom_buffer).unwrap();
+fn main() {
+  // enable logger
+  env_logger::init();
 
-  let mut nintendo_logo: Vec<u8> = vec![
-    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
-    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
-    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
-  ];
+  let args = match Args::parse(env::args().skip(1)) {
+    Ok(args) => args,
+    Err(e) => {
+      log::error!("Error: {}", e);
+      log::info!("Usage: cargo run [--entry <NAME>] [--model dmg|mgb|sgb|cgb|agb] [--boot-rom <PATH> | --skip-boot] <ROM_PATH> [PATCH_PATH...]");
+      process::exit(1);
+    }
+  };
+  let rom_path = args.rom_path;
+
+  // Patches given after the ROM, or else the ones next to it with the same
+  // name (game.gb -> game.ips, game.ups, game.bps).
+  let mut options = LoadOptions {
+    patches: args.patches,
+    archive_entry: args.entry,
+  };
+  if options.patches.is_empty() {
+    options.patches = cartridge::find_patches(&rom_path);
+  }
+  for patch in &options.patches {
+    log::info!("Applying patch {}", patch.display());
+  }
 
+  let mut cartridge = match Cartridge::from_file_with_options(&rom_path, &options) {
+    Ok(cartridge) => cartridge,
+    Err(e) => {
+      log::error!("Error: {}: {}", rom_path, e);
+      process::exit(1);
+    }
+  };
 
-  let mut bus = Bus::new();
-  let mut cpu = Cpu::new();
-  cpu.bus_connect(&mut bus);
+  // Battery backed RAM lives next to the ROM, e.g. game.gb -> game.sav
+  let save_path = Path::new(&rom_path).with_extension("sav");
+  if cartridge.has_battery() {
+    if let Err(e) = cartridge.load_save(&save_path) {
+      log::error!("Error: {}: {}", save_path.display(), e);
+    }
+  }
 
-  bus.cpu_connect(&mut cpu);
+  let model = args
+    .model
+    .unwrap_or_else(|| Model::detect(&cartridge.header));
+  log::info!("Model: {}", model);
+
+  // Without a boot ROM the emulator starts at the cartridge entry point.
+  let boot_rom = match args.boot_rom.filter(|_| !args.skip_boot) {
+    Some(path) => match BootRom::from_file(&path) {
+      Ok(boot_rom) => {
+        if boot_rom.size() != model.boot_rom_size() {
+          log::warn!("{} boot rom doesn't match model {}", path.display(), model);
+        }
+        Some(boot_rom)
+      }
+      Err(e) => {
+        log::error!("Error: {}: {}", path.display(), e);
+        process::exit(1);
+      }
+    },
+    None => None,
+  };
 
-  bus.memory[0..=255].copy_from_slice(&mut rom_buffer[0..=255]);
-  bus.memory[0x104..=0x133].copy_from_slice(&mut nintendo_logo);
+  let mut gameboy = GameBoy::new(cartridge, model, boot_rom);
 
-  let mut debugger = WinSDL::new("Debugger", 950, 600).unwrap();
+  let mut debugger = WinSDL::new("Debugger", 1300, 600).unwrap();
 
   let mut fps_counter = FpsCounter::new();
   let mut frame_counter = FrameCounter::new();
 
-  let mut step_error = 0;
+  // The first CPU error. The PC view follows the faulting instruction from
+  // then on.
+  let mut fault: Option<CpuError> = None;
+  // R toggles running at full speed, Space steps one instruction.
+  let mut running = false;
+  let mut rumble = false;
+  let mut last_save = Instant::now();
 
-  // let mut test = TestSuite::new(&mut cpu, &mut bus.memory);
+  // let mut test = TestSuite::<FlatRam>::new();
   // test.run_test("./roms/json_tests/20.json");
 
-  cpu.debug();
-  cpu.view_memory_at(&bus.memory, cpu.reg.pc as usize, 8);
-
-  // enable logger
-  env_logger::init();
+  gameboy.cpu.debug();
+  gameboy.cpu.view_memory_at(gameboy.cpu.reg.pc as usize, 8);
 
   'running: loop {
     let fps = fps_counter.get_fps();
@@ -70,10 +159,41 @@ fn main() {
     debugger.canvas.set_draw_color(Color::RGB(0, 0, 0));
     debugger.canvas.clear();
 
-    debugger.draw_cpu_registers(&cpu, 10, 10);
-    debugger.draw_memory_view(&bus.memory, 0x0000, 10, 300, 16, 15);
-    debugger.draw_memory_view(&bus.memory, cpu.reg.pc, 10, 230, 0, 6);
-    debugger.draw_memory_view(&bus.memory, 0x0104, 100, 80, 2, 15);
+    if running {
+      if let Err(e) = gameboy.run_frame() {
+        log::error!("{}", e);
+        fault.get_or_insert(e);
+        running = false;
+      }
+    }
+
+    let cpu = &gameboy.cpu;
+    debugger.draw_cpu_registers(cpu, 10, 10);
+    debugger.draw_memory_view(&cpu.bus, 0x0000, 10, 300, 16, 15);
+    let pc_view = fault.map_or(cpu.reg.pc, |e| e.pc());
+    debugger.draw_memory_view(&cpu.bus, pc_view, 10, 230, 0, 6);
+    debugger.draw_memory_view(&cpu.bus, 0x0104, 100, 80, 2, 15);
+    debugger.draw_cartridge_header(&gameboy.cartridge().header, 960, 40);
+    debugger.draw_screen(gameboy.framebuffer(), 960, 300, 2);
+
+    while let Some(event) = gameboy.cartridge_mut().poll_event() {
+      match event {
+        CartridgeEvent::Rumble(on) => rumble = on,
+      }
+    }
+    if rumble {
+      debugger.draw_text("RUMBLE", 960, 280);
+    }
+    if let Some(e) = fault {
+      debugger.draw_text(&e.to_string(), 10, 260);
+    }
+
+    if last_save.elapsed() >= SAVE_INTERVAL {
+      if let Err(e) = gameboy.cartridge_mut().flush_save(&save_path) {
+        log::error!("Error: {}: {}", save_path.display(), e);
+      }
+      last_save = Instant::now();
+    }
 
     // debugger.draw_ascii_grid(&bus.memory, 10, 850, 300);
 
@@ -85,22 +205,26 @@ fn main() {
         Event::Quit { .. } => break 'running,
         Event::KeyDown { keycode, .. } => match keycode {
           Some(Keycode::Space) => {
-            let cpu_step = cpu.step();
+            let cpu_step = gameboy.step_instruction();
+            let cpu = &gameboy.cpu;
             cpu.debug();
-            cpu.view_memory_at(&bus.memory, cpu.reg.pc as usize, 8);
+            cpu.view_memory_at(cpu.reg.pc as usize, 8);
 
-            if step_error == 0 {
+            if fault.is_none() {
               if let Err(e) = cpu_step {
                 log::error!("{}", e);
-                step_error = -1
+                fault = Some(e);
               }
             }
           }
 
+          Some(Keycode::R) => running = !running,
+
           Some(Keycode::X) => {
+            let cpu = &mut gameboy.cpu;
             cpu.reg.set_hl(0x7fff);
             cpu.debug();
-            cpu.view_memory_at(&bus.memory, cpu.reg.pc as usize, 8);
+            cpu.view_memory_at(cpu.reg.pc as usize, 8);
           }
 
           _ => (),
@@ -111,15 +235,19 @@ fn main() {
 
     // if step_error == 0 {
     //   // cpu.debug();
-    //   // cpu.view_memory_at(&bus.memory, cpu.reg.pc as usize, 8);
+    //   // cpu.view_memory_at(cpu.reg.pc as usize, 8);
 
     //   if let Err(e) = cpu.step() {
     //     eprintln!("{}", e);
     //     // cpu.debug();
-    //     // cpu.view_memory_at(&bus.memory, cpu.reg.pc as usize, 8);
+    //     // cpu.view_memory_at(cpu.reg.pc as usize, 8);
 
     //     step_error = -1;
     //   }
     // }
   }
+
+  if let Err(e) = gameboy.cartridge_mut().flush_save(&save_path) {
+    log::error!("Error: {}: {}", save_path.display(), e);
+  }
 }
@@ -0,0 +1,44 @@
+/// Memory as seen by the CPU.
+pub trait MemoryInterface {
+  fn read(&self, addr: u16) -> u8;
+
+  fn write(&mut self, addr: u16, data: u8);
+
+  /// Advances the hardware behind the bus by `t_cycles`.
+  fn tick(&mut self, _t_cycles: usize) {}
+
+  /// Debugger write: like `write`, but also stores into read-only memory.
+  fn poke(&mut self, addr: u16, data: u8) {
+    self.write(addr, data);
+  }
+}
+
+/// 64 KiB of plain RAM without any mapping or IO, to test the CPU on its own.
+#[derive(Debug)]
+pub struct FlatRam {
+  pub memory: Vec<u8>,
+}
+
+impl FlatRam {
+  pub fn new() -> FlatRam {
+    FlatRam {
+      memory: vec![0; 0x10000],
+    }
+  }
+}
+
+impl Default for FlatRam {
+  fn default() -> FlatRam {
+    FlatRam::new()
+  }
+}
+
+impl MemoryInterface for FlatRam {
+  fn read(&self, addr: u16) -> u8 {
+    self.memory[addr as usize]
+  }
+
+  fn write(&mut self, addr: u16, data: u8) {
+    self.memory[addr as usize] = data;
+  }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::boot::{CGB_BOOT_ROM_SIZE, DMG_BOOT_ROM_SIZE};
+use crate::cartridge::{CartridgeHeader, CgbFlag};
+use crate::cpu::Register;
+
+/// Hardware revision being emulated. It decides the state the boot ROM
+/// leaves behind and which hardware features are present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Model {
+  #[default]
+  Dmg,
+  /// Game Boy Pocket / Light.
+  Mgb,
+  /// Super Game Boy.
+  Sgb,
+  Cgb,
+  /// Game Boy Advance running Game Boy software.
+  Agb,
+}
+
+impl Model {
+  /// CGB cartridges get a CGB, everything else a DMG.
+  pub fn detect(header: &CartridgeHeader) -> Model {
+    match header.cgb_flag {
+      CgbFlag::None => Model::Dmg,
+      CgbFlag::Supported | CgbFlag::Only => Model::Cgb,
+    }
+  }
+
+  /// Color palettes, VRAM/WRAM banks, double speed and the CGB registers.
+  pub fn is_cgb(&self) -> bool {
+    matches!(self, Model::Cgb | Model::Agb)
+  }
+
+  /// SGB command packets sent through the joypad register.
+  pub fn has_sgb(&self) -> bool {
+    matches!(self, Model::Sgb)
+  }
+
+  pub fn boot_rom_size(&self) -> usize {
+    if self.is_cgb() {
+      CGB_BOOT_ROM_SIZE
+    } else {
+      DMG_BOOT_ROM_SIZE
+    }
+  }
+
+  /// CPU registers when the boot ROM jumps to 0x0100. Games use A (and B on
+  /// AGB) to tell the hardware apart.
+  pub fn post_boot_registers(&self, header: &CartridgeHeader) -> Register {
+    // The DMG boot ROM leaves H and C set unless the header checksum is 0x00.
+    let dmg_flags = if header.header_checksum == 0 {
+      0x80
+    } else {
+      0xB0
+    };
+    // A CGB running a DMG cartridge sets up compatibility palettes instead.
+    let cgb_mode = header.cgb_flag != CgbFlag::None;
+
+    let (af, bc, de, hl) = match self {
+      Model::Dmg => (0x0100 | dmg_flags, 0x0013, 0x00D8, 0x014D),
+      Model::Mgb => (0xFF00 | dmg_flags, 0x0013, 0x00D8, 0x014D),
+      Model::Sgb => (0x0100, 0x0014, 0x0000, 0xC060),
+      Model::Cgb if cgb_mode => (0x1180, 0x0000, 0xFF56, 0x000D),
+      Model::Cgb => (0x1180, 0x0000, 0x0008, 0x007C),
+      // The AGB boot ROM ends with an extra INC B, which clears Z.
+      Model::Agb if cgb_mode => (0x1100, 0x0100, 0xFF56, 0x000D),
+      Model::Agb => (0x1100, 0x0100, 0x0008, 0x007C),
+    };
+
+    let mut reg = Register::new();
+    reg.set_af(af);
+    reg.set_bc(bc);
+    reg.set_de(de);
+    reg.set_hl(hl);
+    reg.sp = 0xFFFE;
+    reg.pc = 0x0100;
+    reg
+  }
+
+  /// Internal timer counter when the boot ROM jumps to 0x0100, DIV is its
+  /// upper byte.
+  pub fn post_boot_divider(&self) -> u16 {
+    if self.is_cgb() {
+      0x0000
+    } else {
+      0xABCC
+    }
+  }
+
+  /// IO registers when the boot ROM jumps to 0x0100.
+  pub fn post_boot_io(&self) -> Vec<(u16, u8)> {
+    let mut io = vec![
+      (0xFF00, if self.has_sgb() { 0xFF } else { 0xCF }), // P1
+      (0xFF01, 0x00),                                     // SB
+      (0xFF02, if self.is_cgb() { 0x7F } else { 0x7E }),  // SC
+      (0xFF05, 0x00),                                     // TIMA
+      (0xFF06, 0x00),                                     // TMA
+      (0xFF07, 0xF8),                                     // TAC
+      (0xFF0F, 0xE1),                                     // IF
+      // NR52 first, the APU ignores NR10-NR51 while it's off.
+      (0xFF26, if self.has_sgb() { 0xF0 } else { 0xF1 }), // NR52
+      (0xFF10, 0x80),                                     // NR10
+      (0xFF11, 0xBF),                                     // NR11
+      (0xFF12, 0xF3),                                     // NR12
+      (0xFF13, 0xFF),                                     // NR13
+      (0xFF14, 0xBF),                                     // NR14
+      (0xFF16, 0x3F),                                     // NR21
+      (0xFF17, 0x00),                                     // NR22
+      (0xFF18, 0xFF),                                     // NR23
+      (0xFF19, 0xBF),                                     // NR24
+      (0xFF1A, 0x7F),                                     // NR30
+      (0xFF1B, 0xFF),                                     // NR31
+      (0xFF1C, 0x9F),                                     // NR32
+      (0xFF1D, 0xFF),                                     // NR33
+      (0xFF1E, 0xBF),                                     // NR34
+      (0xFF20, 0xFF),                                     // NR41
+      (0xFF21, 0x00),                                     // NR42
+      (0xFF22, 0x00),                                     // NR43
+      (0xFF23, 0xBF),                                     // NR44
+      (0xFF24, 0x77),                                     // NR50
+      (0xFF25, 0xF3),                                     // NR51
+      (0xFF40, 0x91),                                     // LCDC
+      (0xFF41, 0x85),                                     // STAT
+      (0xFF42, 0x00),                                     // SCY
+      (0xFF43, 0x00),                                     // SCX
+      (0xFF44, 0x00),                                     // LY
+      (0xFF45, 0x00),                                     // LYC
+      (0xFF46, if self.is_cgb() { 0x00 } else { 0xFF }),  // DMA
+      (0xFF47, 0xFC),                                     // BGP
+      (0xFF4A, 0x00),                                     // WY
+      (0xFF4B, 0x00),                                     // WX
+    ];
+
+    // Registers that only exist on CGB hardware.
+    if self.is_cgb() {
+      io.extend_from_slice(&[
+        (0xFF4D, 0x7E), // KEY1
+        (0xFF4F, 0xFE), // VBK
+        (0xFF56, 0x3E), // RP
+        (0xFF70, 0xF8), // SVBK
+      ]);
+    }
+
+    io
+  }
+}
+
+impl fmt::Display for Model {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let name = match self {
+      Model::Dmg => "DMG",
+      Model::Mgb => "MGB",
+      Model::Sgb => "SGB",
+      Model::Cgb => "CGB",
+      Model::Agb => "AGB",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+impl FromStr for Model {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Model, String> {
+    match s.to_ascii_lowercase().as_str() {
+      "dmg" => Ok(Model::Dmg),
+      "mgb" => Ok(Model::Mgb),
+      "sgb" => Ok(Model::Sgb),
+      "cgb" => Ok(Model::Cgb),
+      "agb" => Ok(Model::Agb),
+      _ => Err(format!("unknown model: {}", s)),
+    }
+  }
+}
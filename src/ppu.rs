@@ -0,0 +1,486 @@
+use crate::interrupts::{Interrupt, InterruptController};
+
+pub const LCDC_ADDR: u16 = 0xFF40;
+pub const STAT_ADDR: u16 = 0xFF41;
+pub const SCY_ADDR: u16 = 0xFF42;
+pub const SCX_ADDR: u16 = 0xFF43;
+pub const LY_ADDR: u16 = 0xFF44;
+pub const LYC_ADDR: u16 = 0xFF45;
+pub const BGP_ADDR: u16 = 0xFF47;
+pub const OBP0_ADDR: u16 = 0xFF48;
+pub const OBP1_ADDR: u16 = 0xFF49;
+pub const WY_ADDR: u16 = 0xFF4A;
+pub const WX_ADDR: u16 = 0xFF4B;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// Dots (T-cycles) per line, and lines per frame including VBlank.
+pub const DOTS_PER_LINE: usize = 456;
+pub const LINES_PER_FRAME: u8 = 154;
+
+// Mode 2 takes the first 80 dots of a line. Mode 3 takes at least 172 more,
+// plus the pixels discarded for SCX % 8. The extra dots objects and the
+// window cost on hardware aren't counted.
+pub const OAM_SCAN_DOTS: usize = 80;
+pub const DRAWING_DOTS: usize = 172;
+
+/// Objects the DMG can draw on one line.
+pub const SPRITES_PER_LINE: usize = 10;
+
+// LCDC bits.
+const LCDC_BG_ENABLE: u8 = 1 << 0;
+const LCDC_OBJ_ENABLE: u8 = 1 << 1;
+const LCDC_OBJ_SIZE: u8 = 1 << 2;
+const LCDC_BG_MAP: u8 = 1 << 3;
+const LCDC_TILE_DATA: u8 = 1 << 4;
+const LCDC_WINDOW_ENABLE: u8 = 1 << 5;
+const LCDC_WINDOW_MAP: u8 = 1 << 6;
+const LCDC_LCD_ENABLE: u8 = 1 << 7;
+
+// STAT bits. Bits 3-6 select the interrupt sources, the rest is read only.
+const STAT_COINCIDENCE: u8 = 1 << 2;
+const STAT_HBLANK_INT: u8 = 1 << 3;
+const STAT_VBLANK_INT: u8 = 1 << 4;
+const STAT_OAM_INT: u8 = 1 << 5;
+const STAT_LYC_INT: u8 = 1 << 6;
+const STAT_WRITABLE: u8 = 0b0111_1000;
+
+// OAM attribute bits.
+const OBJ_PALETTE: u8 = 1 << 4;
+const OBJ_X_FLIP: u8 = 1 << 5;
+const OBJ_Y_FLIP: u8 = 1 << 6;
+/// BG and window colors 1-3 are drawn over the object.
+const OBJ_BG_PRIORITY: u8 = 1 << 7;
+
+/// An OAM entry, with the position as stored: Y + 16 and X + 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sprite {
+  pub y: u8,
+  pub x: u8,
+  pub tile: u8,
+  pub flags: u8,
+}
+
+/// PPU mode, as reported in the low bits of STAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+  HBlank = 0,
+  VBlank = 1,
+  /// Mode 2, OAM is searched for the objects on the line.
+  OamScan = 2,
+  /// Mode 3, pixels are sent to the LCD.
+  Drawing = 3,
+}
+
+/// Picture processing unit: VRAM, OAM and the LCD registers.
+///
+/// Each visible line goes through modes 2, 3 and 0, then lines 144-153 are
+/// mode 1. A line is rendered whole when mode 3 ends, from the tile data and
+/// tile maps in VRAM and the objects found during mode 2. The framebuffer
+/// holds one shade per pixel, from 0 (white) to 3 (black), after the palette
+/// has been applied.
+#[derive(Debug)]
+pub struct Ppu {
+  pub vram: [u8; 0x2000],
+  pub oam: [u8; 0xA0],
+  pub lcdc: u8,
+  /// Interrupt source bits of STAT, the rest comes from `mode` and LY == LYC.
+  pub stat: u8,
+  pub scy: u8,
+  pub scx: u8,
+  pub ly: u8,
+  pub lyc: u8,
+  pub bgp: u8,
+  pub obp0: u8,
+  pub obp1: u8,
+  pub wy: u8,
+  pub wx: u8,
+  pub mode: Mode,
+  framebuffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+  // Dots into the current line.
+  dots: usize,
+  // Length of mode 3 on the current line.
+  drawing_dots: usize,
+  // Objects found by the OAM scan of the current line.
+  line_sprites: Vec<Sprite>,
+  // The STAT interrupt is requested when the OR of its enabled sources goes
+  // from low to high, so a source can't fire while another one holds the
+  // line high.
+  stat_line: bool,
+  // The window has its own line counter, which only advances on lines
+  // where the window was drawn.
+  window_line: u8,
+  // LY matched WY at some point this frame.
+  window_triggered: bool,
+  // VBlank started since the last `take_frame_ready`.
+  frame_ready: bool,
+}
+
+impl Ppu {
+  pub fn new() -> Ppu {
+    Ppu {
+      vram: [0; 0x2000],
+      oam: [0; 0xA0],
+      lcdc: 0,
+      stat: 0,
+      scy: 0,
+      scx: 0,
+      ly: 0,
+      lyc: 0,
+      bgp: 0,
+      obp0: 0,
+      obp1: 0,
+      wy: 0,
+      wx: 0,
+      mode: Mode::HBlank,
+      framebuffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+      dots: 0,
+      drawing_dots: DRAWING_DOTS,
+      line_sprites: Vec::new(),
+      stat_line: false,
+      window_line: 0,
+      window_triggered: false,
+      frame_ready: false,
+    }
+  }
+
+  /// The last rendered frame, `SCREEN_WIDTH` x `SCREEN_HEIGHT` shades
+  /// from 0 (white) to 3 (black), row by row.
+  pub fn framebuffer(&self) -> &[u8] {
+    &self.framebuffer
+  }
+
+  pub fn lcd_enabled(&self) -> bool {
+    self.lcdc & LCDC_LCD_ENABLE != 0
+  }
+
+  /// Whether VBlank started (LY 143 to 144) since the last call, meaning
+  /// `framebuffer` holds a whole new frame.
+  pub fn take_frame_ready(&mut self) -> bool {
+    std::mem::take(&mut self.frame_ready)
+  }
+
+  /// Advances the PPU by `t_cycles` dots.
+  pub fn tick(&mut self, t_cycles: usize, interrupts: &mut InterruptController) {
+    if !self.lcd_enabled() {
+      return;
+    }
+
+    for _ in 0..t_cycles {
+      self.step_dot(interrupts);
+      self.update_stat_line(interrupts);
+    }
+  }
+
+  fn step_dot(&mut self, interrupts: &mut InterruptController) {
+    self.dots += 1;
+
+    if self.dots == DOTS_PER_LINE {
+      self.dots = 0;
+      self.ly = (self.ly + 1) % LINES_PER_FRAME;
+
+      match self.ly as usize {
+        SCREEN_HEIGHT => {
+          self.mode = Mode::VBlank;
+          self.frame_ready = true;
+          interrupts.request(Interrupt::VBlank);
+        }
+        0 => {
+          self.window_line = 0;
+          self.window_triggered = false;
+          self.mode = Mode::OamScan;
+        }
+        ly if ly < SCREEN_HEIGHT => self.mode = Mode::OamScan,
+        _ => (),
+      }
+    } else if self.dots == OAM_SCAN_DOTS && (self.ly as usize) < SCREEN_HEIGHT {
+      // The first line after the LCD is turned on skips mode 2 and reports
+      // mode 0 instead, but still starts drawing on time.
+      self.line_sprites = if self.lcdc & LCDC_OBJ_ENABLE != 0 {
+        self.oam_scan(self.ly)
+      } else {
+        Vec::new()
+      };
+      self.drawing_dots = DRAWING_DOTS + (self.scx % 8) as usize;
+      self.mode = Mode::Drawing;
+    } else if self.mode == Mode::Drawing && self.dots == OAM_SCAN_DOTS + self.drawing_dots {
+      self.render_line();
+      self.mode = Mode::HBlank;
+    }
+  }
+
+  /// Requests the STAT interrupt on a rising edge of the STAT line.
+  fn update_stat_line(&mut self, interrupts: &mut InterruptController) {
+    let line = (self.stat & STAT_LYC_INT != 0 && self.ly == self.lyc)
+      || match self.mode {
+        Mode::HBlank => self.stat & STAT_HBLANK_INT != 0,
+        Mode::VBlank => self.stat & STAT_VBLANK_INT != 0,
+        Mode::OamScan => self.stat & STAT_OAM_INT != 0,
+        Mode::Drawing => false,
+      };
+
+    if line && !self.stat_line {
+      interrupts.request(Interrupt::LcdStat);
+    }
+    self.stat_line = line;
+  }
+
+  // The CPU can't reach VRAM while the PPU reads it in mode 3, nor OAM in
+  // modes 2 and 3. Reads return 0xFF and writes are ignored.
+  fn vram_accessible(&self) -> bool {
+    self.mode != Mode::Drawing
+  }
+
+  fn oam_accessible(&self) -> bool {
+    !matches!(self.mode, Mode::OamScan | Mode::Drawing)
+  }
+
+  /// CPU read from VRAM, 0xFF during mode 3.
+  pub fn read_vram(&self, addr: u16) -> u8 {
+    if self.vram_accessible() {
+      self.vram[(addr - 0x8000) as usize]
+    } else {
+      0xFF
+    }
+  }
+
+  /// CPU write to VRAM, ignored during mode 3.
+  pub fn write_vram(&mut self, addr: u16, data: u8) {
+    if self.vram_accessible() {
+      self.vram[(addr - 0x8000) as usize] = data;
+    }
+  }
+
+  /// CPU read from OAM, 0xFF during modes 2 and 3.
+  pub fn read_oam(&self, addr: u16) -> u8 {
+    if self.oam_accessible() {
+      self.oam[(addr - 0xFE00) as usize]
+    } else {
+      0xFF
+    }
+  }
+
+  /// CPU write to OAM, ignored during modes 2 and 3.
+  pub fn write_oam(&mut self, addr: u16, data: u8) {
+    if self.oam_accessible() {
+      self.oam[(addr - 0xFE00) as usize] = data;
+    }
+  }
+
+  /// 8 or 16, from LCDC bit 2.
+  pub fn sprite_height(&self) -> u8 {
+    if self.lcdc & LCDC_OBJ_SIZE != 0 {
+      16
+    } else {
+      8
+    }
+  }
+
+  /// Objects on line `ly`: the first 10 in OAM order, then sorted by X. On
+  /// DMG the object with the smaller X is drawn on top, and OAM order breaks
+  /// ties. Objects off screen horizontally still count towards the limit.
+  pub fn oam_scan(&self, ly: u8) -> Vec<Sprite> {
+    let height = self.sprite_height();
+    let mut sprites: Vec<Sprite> = self
+      .oam
+      .chunks_exact(4)
+      .map(|entry| Sprite {
+        y: entry[0],
+        x: entry[1],
+        tile: entry[2],
+        flags: entry[3],
+      })
+      .filter(|sprite| {
+        let line = ly as i16 + 16 - sprite.y as i16;
+        (0..height as i16).contains(&line)
+      })
+      .take(SPRITES_PER_LINE)
+      .collect();
+
+    // Stable, so OAM order is kept for equal X.
+    sprites.sort_by_key(|sprite| sprite.x);
+    sprites
+  }
+
+  /// Color index (0-3) of pixel (`x`, `y`) of tile `index` in the 0x8000 or
+  /// 0x8800 block.
+  fn tile_data_pixel(&self, index: u8, unsigned: bool, x: u8, y: u8) -> u8 {
+    // 0x8000 addressing uses unsigned tile numbers, 0x8800 addressing signed
+    // ones relative to 0x9000.
+    let addr = if unsigned {
+      0x8000 + index as u16 * 16
+    } else {
+      (0x9000 + index as i8 as i32 * 16) as u16
+    };
+
+    let row = addr as usize - 0x8000 + y as usize * 2;
+    let bit = 7 - x;
+    let low = (self.vram[row] >> bit) & 1;
+    let high = (self.vram[row + 1] >> bit) & 1;
+    (high << 1) | low
+  }
+
+  /// Color index (0-3) of pixel (`x`, `y`) of the tile at `tile` in the map.
+  fn tile_pixel(&self, map: u16, tile: u16, x: u8, y: u8) -> u8 {
+    let index = self.vram[(map + tile) as usize - 0x8000];
+    self.tile_data_pixel(index, self.lcdc & LCDC_TILE_DATA != 0, x, y)
+  }
+
+  /// Color index (0-3) of `sprite` at screen column `x` on line `ly`, 0 where
+  /// it's transparent or doesn't cover `x`.
+  fn sprite_pixel(&self, sprite: &Sprite, x: u8, ly: u8) -> u8 {
+    let column = x as i16 + 8 - sprite.x as i16;
+    if !(0..8).contains(&column) {
+      return 0;
+    }
+
+    let height = self.sprite_height();
+    let mut line = (ly as i16 + 16 - sprite.y as i16) as u8;
+    let mut column = column as u8;
+    if sprite.flags & OBJ_Y_FLIP != 0 {
+      line = height - 1 - line;
+    }
+    if sprite.flags & OBJ_X_FLIP != 0 {
+      column = 7 - column;
+    }
+
+    // 8x16 objects ignore bit 0 of the tile number, the bottom half is the
+    // next tile.
+    let tile = if height == 16 {
+      (sprite.tile & 0xFE) + line / 8
+    } else {
+      sprite.tile
+    };
+    self.tile_data_pixel(tile, true, column, line % 8)
+  }
+
+  fn render_line(&mut self) {
+    let ly = self.ly;
+    if ly == self.wy {
+      self.window_triggered = true;
+    }
+
+    let bg_enabled = self.lcdc & LCDC_BG_ENABLE != 0;
+    // WX is the window position plus 7.
+    let window_visible =
+      bg_enabled && self.lcdc & LCDC_WINDOW_ENABLE != 0 && self.window_triggered && self.wx <= 166;
+    let window_x = self.wx as i16 - 7;
+
+    let bg_map = if self.lcdc & LCDC_BG_MAP != 0 {
+      0x9C00
+    } else {
+      0x9800
+    };
+    let window_map = if self.lcdc & LCDC_WINDOW_MAP != 0 {
+      0x9C00
+    } else {
+      0x9800
+    };
+
+    let mut window_drawn = false;
+    for x in 0..SCREEN_WIDTH {
+      // With the BG disabled both layers are blank (color 0).
+      let color = if !bg_enabled {
+        0
+      } else if window_visible && x as i16 >= window_x {
+        window_drawn = true;
+        let wx = (x as i16 - window_x) as u8;
+        let wy = self.window_line;
+        let tile = (wy as u16 / 8) * 32 + wx as u16 / 8;
+        self.tile_pixel(window_map, tile, wx % 8, wy % 8)
+      } else {
+        let bx = self.scx.wrapping_add(x as u8);
+        let by = self.scy.wrapping_add(ly);
+        let tile = (by as u16 / 8) * 32 + bx as u16 / 8;
+        self.tile_pixel(bg_map, tile, bx % 8, by % 8)
+      };
+
+      let mut shade = (self.bgp >> (color * 2)) & 0b11;
+
+      // The first opaque object wins, even if the BG then hides it.
+      let sprite = self.line_sprites.iter().find_map(|sprite| {
+        let color = self.sprite_pixel(sprite, x as u8, ly);
+        (color != 0).then_some((sprite, color))
+      });
+      if let Some((sprite, sprite_color)) = sprite {
+        if sprite.flags & OBJ_BG_PRIORITY == 0 || color == 0 {
+          let palette = if sprite.flags & OBJ_PALETTE != 0 {
+            self.obp1
+          } else {
+            self.obp0
+          };
+          shade = (palette >> (sprite_color * 2)) & 0b11;
+        }
+      }
+
+      self.framebuffer[ly as usize * SCREEN_WIDTH + x] = shade;
+    }
+
+    if window_drawn {
+      self.window_line += 1;
+    }
+  }
+
+  /// Reads one of the LCD registers (FF40-FF45, FF47-FF4B).
+  pub fn read(&self, addr: u16) -> u8 {
+    match addr {
+      LCDC_ADDR => self.lcdc,
+      STAT_ADDR => {
+        // Bit 7 is unused and reads as 1. With the LCD off the mode reads 0.
+        let coincidence = if self.ly == self.lyc {
+          STAT_COINCIDENCE
+        } else {
+          0
+        };
+        0x80 | self.stat | coincidence | self.mode as u8
+      }
+      SCY_ADDR => self.scy,
+      SCX_ADDR => self.scx,
+      LY_ADDR => self.ly,
+      LYC_ADDR => self.lyc,
+      BGP_ADDR => self.bgp,
+      OBP0_ADDR => self.obp0,
+      OBP1_ADDR => self.obp1,
+      WY_ADDR => self.wy,
+      _ => self.wx,
+    }
+  }
+
+  /// Writes one of the LCD registers (FF40-FF45, FF47-FF4B).
+  pub fn write(&mut self, addr: u16, data: u8) {
+    match addr {
+      LCDC_ADDR => {
+        let was_enabled = self.lcd_enabled();
+        self.lcdc = data;
+        // Turning the LCD off stops the PPU at the top of the screen in mode
+        // 0, turning it on starts line 0 from there.
+        if was_enabled != self.lcd_enabled() {
+          self.ly = 0;
+          self.dots = 0;
+          self.mode = Mode::HBlank;
+          self.stat_line = false;
+          self.window_line = 0;
+          self.window_triggered = false;
+        }
+      }
+      STAT_ADDR => self.stat = data & STAT_WRITABLE,
+      SCY_ADDR => self.scy = data,
+      SCX_ADDR => self.scx = data,
+      // LY is read only.
+      LY_ADDR => (),
+      LYC_ADDR => self.lyc = data,
+      BGP_ADDR => self.bgp = data,
+      OBP0_ADDR => self.obp0 = data,
+      OBP1_ADDR => self.obp1 = data,
+      WY_ADDR => self.wy = data,
+      _ => self.wx = data,
+    }
+  }
+}
+
+impl Default for Ppu {
+  fn default() -> Ppu {
+    Ppu::new()
+  }
+}
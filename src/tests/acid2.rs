@@ -0,0 +1,66 @@
+// Runs dmg-acid2 (https://github.com/mattcurrie/dmg-acid2) and compares the
+// screen with its reference image. The ROM and the image are not part of the
+// repository, so the test is ignored by default. To run it, put the v1.0
+// release ROM at `roms/dmg-acid2.gb` (or point `GB_ACID2` at it) and the
+// repository's `img/reference-dmg.png` next to it as `dmg-acid2.png`, then:
+//
+//   cargo test dmg_acid2 -- --ignored
+//
+// It hasn't been run against the real ROM yet, so the PPU isn't known to
+// render a pixel-identical screen.
+use std::env;
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::cartridge::Cartridge;
+use crate::gameboy::GameBoy;
+use crate::model::Model;
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// The test draws its final screen well within this many frames.
+const FRAMES: usize = 60;
+
+// Shades (0-3) of a grayscale or RGB(A) reference image, white being 0.
+fn reference_shades(path: &PathBuf) -> Vec<u8> {
+  let mut decoder = png::Decoder::new(File::open(path).unwrap());
+  decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+  let mut reader = decoder.read_info().unwrap();
+  let mut image = vec![0; reader.output_buffer_size()];
+  let info = reader.next_frame(&mut image).unwrap();
+
+  assert_eq!((info.width as usize, info.height as usize), (SCREEN_WIDTH, SCREEN_HEIGHT));
+
+  let channels = info.color_type.samples();
+  image[..info.buffer_size()]
+    .chunks_exact(channels)
+    .map(|pixel| (((255 - pixel[0] as u32) * 3 + 127) / 255) as u8)
+    .collect()
+}
+
+#[test]
+#[ignore = "needs roms/dmg-acid2.gb and dmg-acid2.png"]
+fn dmg_acid2() {
+  let rom = env::var("GB_ACID2")
+    .map(PathBuf::from)
+    .unwrap_or_else(|_| PathBuf::from("./roms/dmg-acid2.gb"));
+
+  let cartridge = Cartridge::from_file(&rom).unwrap_or_else(|e| panic!("{}: {}", rom.display(), e));
+  let mut gameboy = GameBoy::new(cartridge, Model::Dmg, None);
+  for _ in 0..FRAMES {
+    gameboy.run_frame().unwrap();
+  }
+
+  let expected = reference_shades(&rom.with_extension("png"));
+  let actual = gameboy.framebuffer();
+  let mismatches: Vec<(usize, usize)> = (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+    .filter(|&i| actual[i] != expected[i])
+    .map(|i| (i % SCREEN_WIDTH, i / SCREEN_WIDTH))
+    .collect();
+
+  assert!(
+    mismatches.is_empty(),
+    "{} pixels differ, first at {:?}",
+    mismatches.len(),
+    mismatches.first()
+  );
+}
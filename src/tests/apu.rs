@@ -0,0 +1,69 @@
+use crate::apu::{Apu, NR10_ADDR, NR52_ADDR, WAVE_RAM_ADDR};
+use crate::bus::Bus;
+use crate::memory::MemoryInterface;
+
+fn powered_apu() -> Apu {
+  let mut apu = Apu::new();
+  apu.write(NR52_ADDR, 0x80);
+  apu
+}
+
+#[test]
+fn write_only_bits_read_as_one() {
+  let mut apu = powered_apu();
+
+  for addr in 0xFF10..=0xFF25 {
+    apu.write(addr, 0x00);
+  }
+  let values: Vec<u8> = (0xFF10..=0xFF26).map(|addr| apu.read(addr)).collect();
+  assert_eq!(
+    values,
+    [
+      0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF,
+      0xFF, 0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0xF0,
+    ]
+  );
+
+  for addr in 0xFF27..=0xFF2F {
+    assert_eq!(apu.read(addr), 0xFF, "{:04X}", addr);
+  }
+}
+
+#[test]
+fn power_off_clears_and_locks_registers() {
+  let mut apu = powered_apu();
+  apu.write(0xFF24, 0x77);
+  apu.write(WAVE_RAM_ADDR, 0x12);
+
+  apu.write(NR52_ADDR, 0x00);
+  assert_eq!(apu.read(NR52_ADDR), 0x70);
+  assert_eq!(apu.read(0xFF24), 0x00);
+
+  apu.write(0xFF24, 0x77);
+  assert_eq!(apu.read(0xFF24), 0x00);
+
+  // Wave RAM isn't affected by the power switch.
+  apu.write(WAVE_RAM_ADDR + 1, 0x34);
+  assert_eq!(apu.read(WAVE_RAM_ADDR), 0x12);
+  assert_eq!(apu.read(WAVE_RAM_ADDR + 1), 0x34);
+}
+
+#[test]
+fn channel_status_bits_are_read_only() {
+  let mut apu = powered_apu();
+
+  apu.write(NR52_ADDR, 0x8F);
+  assert_eq!(apu.read(NR52_ADDR), 0xF0);
+}
+
+#[test]
+fn bus_routes_sound_registers_to_the_apu() {
+  let mut bus = Bus::new();
+
+  bus.write(NR52_ADDR, 0x80);
+  bus.write(NR10_ADDR, 0x7F);
+  bus.write(0xFF3F, 0xAB);
+  assert_eq!(bus.apu.registers[0], 0x7F);
+  assert_eq!(bus.read(NR10_ADDR), 0xFF);
+  assert_eq!(bus.apu.wave_ram[0x0F], 0xAB);
+}
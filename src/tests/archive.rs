@@ -0,0 +1,148 @@
+use std::io::Write;
+
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+
+use crate::cartridge::archive::{extract, ArchiveError};
+use crate::cartridge::{Cartridge, CartridgeError, LoadOptions, MAX_ROM_SIZE};
+use crate::tests::cartridge::rom_with_header;
+use crate::utils::crc32::crc32;
+
+// Builds a zip file with the given (name, data, deflate) entries.
+fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+  let mut out = Vec::new();
+  let mut central = Vec::new();
+
+  for &(name, data, deflate) in files {
+    let (method, compressed) = if deflate {
+      let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+      encoder.write_all(data).unwrap();
+      (8u16, encoder.finish().unwrap())
+    } else {
+      (0u16, data.to_vec())
+    };
+
+    let mut fields = Vec::new();
+    fields.extend_from_slice(&method.to_le_bytes());
+    fields.extend_from_slice(&[0; 4]); // time, date
+    fields.extend_from_slice(&crc32(data).to_le_bytes());
+    fields.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+    fields.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    fields.extend_from_slice(&[0; 2]); // extra length
+
+    let local_header = out.len() as u32;
+    out.extend_from_slice(b"PK\x03\x04");
+    out.extend_from_slice(&[20, 0, 0, 0]); // version, flags
+    out.extend_from_slice(&fields);
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(&compressed);
+
+    central.extend_from_slice(b"PK\x01\x02");
+    central.extend_from_slice(&[20, 0, 20, 0, 0, 0]); // versions, flags
+    central.extend_from_slice(&fields);
+    central.extend_from_slice(&[0; 10]); // comment length, disk, attributes
+    central.extend_from_slice(&local_header.to_le_bytes());
+    central.extend_from_slice(name.as_bytes());
+  }
+
+  let central_offset = out.len() as u32;
+  out.extend_from_slice(&central);
+  out.extend_from_slice(b"PK\x05\x06");
+  out.extend_from_slice(&[0; 4]); // disk numbers
+  out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+  out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+  out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+  out.extend_from_slice(&central_offset.to_le_bytes());
+  out.extend_from_slice(&[0; 2]); // comment length
+  out
+}
+
+#[test]
+fn raw_roms_are_passed_through() {
+  let rom = rom_with_header("RAW", 0x00, 0x00, 0x00);
+  assert_eq!(extract(rom.clone(), None).unwrap(), rom);
+}
+
+#[test]
+fn gzip_is_decompressed() {
+  let rom = rom_with_header("GZIP", 0x00, 0x00, 0x00);
+  let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+  encoder.write_all(&rom).unwrap();
+
+  assert_eq!(extract(encoder.finish().unwrap(), None).unwrap(), rom);
+}
+
+#[test]
+fn zip_picks_first_rom_entry() {
+  let first = rom_with_header("FIRST", 0x00, 0x00, 0x00);
+  let second = rom_with_header("SECOND", 0x00, 0x00, 0x00);
+  let data = zip(&[
+    ("readme.txt", b"not a rom", false),
+    ("roms/first.GB", &first, true),
+    ("second.gbc", &second, false),
+  ]);
+
+  assert_eq!(extract(data.clone(), None).unwrap(), first);
+  assert_eq!(extract(data.clone(), Some("second.gbc")).unwrap(), second);
+  assert_eq!(extract(data.clone(), Some("roms/first.GB")).unwrap(), first);
+  assert!(matches!(extract(data, Some("missing.gb")), Err(ArchiveError::EntryNotFound(_))));
+}
+
+#[test]
+fn zip_errors() {
+  let data = zip(&[("readme.txt", b"not a rom", true)]);
+  assert!(matches!(extract(data, None), Err(ArchiveError::NoRom)));
+
+  let mut data = zip(&[("game.gb", b"corrupted", false)]);
+  data[30 + "game.gb".len()] ^= 0xFF;
+  assert!(matches!(extract(data, None), Err(ArchiveError::ChecksumMismatch { .. })));
+
+  assert!(matches!(extract(b"PK\x03\x04".to_vec(), None), Err(ArchiveError::Corrupt(_))));
+}
+
+#[test]
+fn rejects_entries_larger_than_any_rom() {
+  let mut data = zip(&[("game.gb", b"tiny", true)]);
+  let central = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+  data[central + 24..central + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert!(matches!(extract(data, None), Err(ArchiveError::Corrupt(_))));
+
+  // Inflating stops past the size the central directory declares.
+  let mut data = zip(&[("game.gb", &[0; 0x1000], true)]);
+  let central = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+  data[central + 24..central + 28].copy_from_slice(&16u32.to_le_bytes());
+  assert!(matches!(extract(data, None), Err(ArchiveError::Corrupt(_))));
+}
+
+#[test]
+fn rejects_gzip_larger_than_any_rom() {
+  let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+  encoder.write_all(&vec![0; MAX_ROM_SIZE + 1]).unwrap();
+
+  assert!(matches!(
+    extract(encoder.finish().unwrap(), None),
+    Err(ArchiveError::Corrupt(_))
+  ));
+}
+
+#[test]
+fn loads_cartridge_from_zip() {
+  let path = std::env::temp_dir().join(format!("gb-archive-test-{}.zip", std::process::id()));
+  let rom = rom_with_header("ZIPPED", 0x00, 0x00, 0x00);
+  std::fs::write(&path, zip(&[("zipped.gb", &rom, true)])).unwrap();
+
+  let cartridge = Cartridge::from_file(&path).unwrap();
+  assert_eq!(cartridge.header.title, "ZIPPED");
+
+  let options = LoadOptions {
+    archive_entry: Some("other.gb".to_string()),
+    ..LoadOptions::default()
+  };
+  assert!(matches!(
+    Cartridge::from_file_with_options(&path, &options),
+    Err(CartridgeError::Archive(ArchiveError::EntryNotFound(_)))
+  ));
+
+  std::fs::remove_file(&path).unwrap();
+}
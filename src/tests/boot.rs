@@ -0,0 +1,86 @@
+use crate::boot::{self, BootRom, BootRomError};
+use crate::cartridge::Cartridge;
+use crate::cpu::Cpu;
+use crate::memory::MemoryInterface;
+use crate::tests::cartridge::rom_with_header;
+
+fn setup(boot_rom: Vec<u8>) -> Cpu {
+  let mut rom = rom_with_header("BOOT", 0x00, 0x00, 0x00);
+  // NOP at the entry point, and a marker past the DMG boot ROM.
+  rom[0x0100] = 0x00;
+  rom[0x0200] = 0xCA;
+
+  let mut cpu = Cpu::new();
+  cpu
+    .bus
+    .insert_cartridge(Cartridge::from_bytes(rom).unwrap());
+  cpu.bus.boot_rom = Some(BootRom::from_bytes(boot_rom).unwrap());
+  cpu
+}
+
+#[test]
+fn boot_rom_overlays_cartridge_until_ff50_write() {
+  let mut boot_rom = vec![0x00; 0x100];
+  // LD A,0x01; LDH (0x50),A at the end of the boot ROM
+  boot_rom[0xFC..].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]);
+  let mut cpu = setup(boot_rom);
+
+  assert_eq!(cpu.bus.read(0x00FC), 0x3E);
+  assert_eq!(cpu.bus.read(0x0147), 0x00);
+
+  cpu.reg.pc = 0x00FC;
+  cpu.step().unwrap();
+  assert_eq!(cpu.bus.read(0x00FC), 0x3E);
+  cpu.step().unwrap();
+
+  assert_eq!(cpu.reg.pc, 0x0100);
+  assert!(cpu.bus.boot_rom.is_none());
+  assert_eq!(cpu.bus.read(0x00FC), 0x00);
+  assert_eq!(cpu.bus.read(0xFF50), 0xFF);
+}
+
+#[test]
+fn writing_zero_to_ff50_keeps_boot_rom_mapped() {
+  let mut cpu = setup(vec![0x31; 0x100]);
+  cpu.bus.write(0xFF50, 0x00);
+  assert_eq!(cpu.bus.read(0x0000), 0x31);
+}
+
+#[test]
+fn cgb_boot_rom_leaves_header_visible() {
+  let cpu = setup(vec![0x31; 0x900]);
+  assert_eq!(cpu.bus.read(0x00FF), 0x31);
+  assert_eq!(cpu.bus.read(0x0134), b'B');
+  assert_eq!(cpu.bus.read(0x0200), 0x31);
+  assert_eq!(cpu.bus.read(0x0900), 0x00);
+}
+
+#[test]
+fn rejects_boot_rom_with_wrong_size() {
+  assert!(matches!(
+    BootRom::from_bytes(vec![0; 0x200]),
+    Err(BootRomError::InvalidSize(0x200))
+  ));
+}
+
+#[test]
+fn skip_boot_sets_post_boot_state() {
+  let mut cpu = setup(vec![0x00; 0x100]);
+  boot::skip_boot(&mut cpu);
+
+  assert!(cpu.bus.boot_rom.is_none());
+  assert_eq!(cpu.reg.get_af(), 0x01B0);
+  assert_eq!(cpu.reg.get_bc(), 0x0013);
+  assert_eq!(cpu.reg.get_de(), 0x00D8);
+  assert_eq!(cpu.reg.get_hl(), 0x014D);
+  assert_eq!(cpu.reg.sp, 0xFFFE);
+  assert_eq!(cpu.reg.pc, 0x0100);
+
+  assert_eq!(cpu.bus.read(0xFF40), 0x91);
+  assert_eq!(cpu.bus.read(0xFF47), 0xFC);
+  assert_eq!(cpu.bus.read(0xFF04), 0xAB);
+  assert_eq!(cpu.bus.read(0xFF0F), 0xE1);
+  assert_eq!(cpu.bus.read(0xFF11), 0xBF);
+  assert_eq!(cpu.bus.read(0xFF26), 0xF1);
+  assert_eq!(cpu.bus.read(0xFFFF), 0x00);
+}
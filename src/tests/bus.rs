@@ -0,0 +1,113 @@
+use super::cpu_with_program;
+use crate::bus::Bus;
+use crate::cartridge::Cartridge;
+use crate::cpu::Cpu;
+use crate::memory::{FlatRam, MemoryInterface};
+
+#[test]
+fn interrupt_enable_register_is_addressable() {
+  let mut bus = Bus::new();
+
+  bus.write(0xFFFF, 0x1F);
+  assert_eq!(bus.read(0xFFFF), 0x1F);
+  assert_eq!(bus.interrupts.enable, 0x1F);
+}
+
+#[test]
+fn ldh_writes_interrupt_enable_register() {
+  // LD A, 0x05; LDH (0xFF), A; LDH A, (0xFF)
+  let mut cpu = cpu_with_program(&[0x3E, 0x05, 0xE0, 0xFF, 0xF0, 0xFF]);
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  assert_eq!(cpu.bus.read(0xFFFF), 0x05);
+
+  cpu.reg.a = 0;
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.a, 0x05);
+}
+
+#[test]
+fn every_address_is_mapped() {
+  let mut bus = Bus::new();
+
+  for addr in 0..=0xFFFF {
+    bus.write(addr, 0x00);
+    bus.read(addr);
+  }
+}
+
+#[test]
+fn unmapped_io_reads_ff() {
+  let mut bus = Bus::new();
+
+  let unmapped = [0xFF03]
+    .into_iter()
+    .chain(0xFF08..=0xFF0E)
+    .chain(0xFF4C..=0xFF7F);
+  for addr in unmapped.filter(|&addr| addr != 0xFF50) {
+    bus.write(addr, 0x00);
+    assert_eq!(bus.read(addr), 0xFF, "{:04X}", addr);
+  }
+}
+
+#[test]
+fn p1_keeps_select_bits_and_reports_no_buttons_pressed() {
+  let mut bus = Bus::new();
+
+  for (select, expected) in [(0x20, 0xEF), (0x10, 0xDF), (0x30, 0xFF), (0x00, 0xCF)] {
+    bus.write(0xFF00, select | 0x0F);
+    assert_eq!(bus.read(0xFF00), expected, "{:02X}", select);
+  }
+}
+
+#[test]
+fn rom_is_read_only() {
+  let mut rom = vec![0; 0x8000];
+  rom[0x0000] = 0x12;
+  rom[0x7FFF] = 0x34;
+
+  let mut bus = Bus::new();
+  bus.insert_cartridge(Cartridge::from_bytes(rom).unwrap());
+
+  bus.write(0x0000, 0xAB);
+  bus.write(0x7FFF, 0xCD);
+  assert_eq!(bus.read(0x0000), 0x12);
+  assert_eq!(bus.read(0x7FFF), 0x34);
+}
+
+#[test]
+fn echo_ram_mirrors_work_ram() {
+  let mut bus = Bus::new();
+
+  bus.write(0xC123, 0x42);
+  assert_eq!(bus.read(0xE123), 0x42);
+
+  bus.write(0xFDFF, 0x24);
+  assert_eq!(bus.read(0xDDFF), 0x24);
+}
+
+#[test]
+fn unusable_region_ignores_writes() {
+  let mut bus = Bus::new();
+
+  bus.write(0xFEA0, 0x42);
+  assert_eq!(bus.read(0xFEA0), 0x00);
+}
+
+#[test]
+fn cpu_runs_on_a_flat_ram_bus() {
+  // LD (HL), A; LD B, (HL)
+  let mut cpu = Cpu::with_bus(FlatRam::new());
+  cpu.bus.write(0x0000, 0x77);
+  cpu.bus.write(0x0001, 0x46);
+  cpu.reg.set_hl(0x0200);
+  cpu.reg.a = 0x42;
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+
+  // No ROM or IO mapping: the write lands at 0x0200 and reads back.
+  assert_eq!(cpu.bus.memory[0x0200], 0x42);
+  assert_eq!(cpu.reg.b, 0x42);
+}
@@ -0,0 +1,452 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::cartridge::{
+  Cartridge, CartridgeError, CartridgeEvent, CartridgeHeader, CgbFlag, Clock, Destination,
+};
+
+// Clock advanced by hand, shared between the test and the cartridge.
+#[derive(Debug, Default, Clone)]
+struct ManualClock(Rc<Cell<u64>>);
+
+impl ManualClock {
+  fn advance(&self, seconds: u64) {
+    self.0.set(self.0.get() + seconds);
+  }
+}
+
+impl Clock for ManualClock {
+  fn now(&self) -> u64 {
+    self.0.get()
+  }
+}
+
+// Builds a ROM image with a valid header checksum.
+pub fn rom_with_header(title: &str, cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+  let mut rom = vec![0; 0x8000 << rom_size];
+  rom[0x134..0x134 + title.len()].copy_from_slice(title.as_bytes());
+  rom[0x147] = cartridge_type;
+  rom[0x148] = rom_size;
+  rom[0x149] = ram_size;
+
+  rom[0x14D] = rom[0x134..=0x14C]
+    .iter()
+    .fold(0u8, |x, &b| x.wrapping_sub(b).wrapping_sub(1));
+  rom
+}
+
+#[test]
+fn parses_header_fields() {
+  let mut rom = rom_with_header("POKEMON GOLD", 0x10, 0x06, 0x03);
+  rom[0x14A] = 0x01;
+  rom[0x14B] = 0x33;
+  rom[0x144..0x146].copy_from_slice(b"01");
+  rom[0x146] = 0x03;
+  rom[0x14C] = 0x01;
+
+  let header = CartridgeHeader::parse(&rom).unwrap();
+
+  assert_eq!(header.title, "POKEMON GOLD");
+  assert_eq!(header.manufacturer_code, None);
+  assert_eq!(header.cgb_flag, CgbFlag::None);
+  assert_eq!(header.licensee_code, "01");
+  assert!(header.sgb_flag);
+  assert_eq!(header.cartridge_type.to_string(), "MBC3+TIMER+RAM+BATTERY");
+  assert_eq!(header.rom_size, 2 * 1024 * 1024);
+  assert_eq!(header.ram_size, 32 * 1024);
+  assert_eq!(header.destination, Destination::Overseas);
+  assert_eq!(header.version, 0x01);
+  assert!(!header.header_checksum_ok());
+}
+
+#[test]
+fn splits_title_and_manufacturer_code_on_cgb_cartridges() {
+  let mut rom = rom_with_header("ZELDA", 0x1B, 0x00, 0x00);
+  rom[0x13F..0x143].copy_from_slice(b"AZ7E");
+  rom[0x143] = 0x80;
+
+  let header = CartridgeHeader::parse(&rom).unwrap();
+
+  assert_eq!(header.title, "ZELDA");
+  assert_eq!(header.manufacturer_code.as_deref(), Some("AZ7E"));
+  assert_eq!(header.cgb_flag, CgbFlag::Supported);
+}
+
+#[test]
+fn verifies_checksums() {
+  let mut rom = rom_with_header("TETRIS", 0x00, 0x00, 0x00);
+  let sum = rom.iter().fold(0u16, |sum, &b| sum.wrapping_add(b as u16));
+  rom[0x14E] = (sum >> 8) as u8;
+  rom[0x14F] = sum as u8;
+
+  let header = CartridgeHeader::parse(&rom).unwrap();
+
+  assert!(header.header_checksum_ok());
+  assert!(header.global_checksum_ok());
+}
+
+#[test]
+fn rejects_invalid_roms() {
+  assert!(matches!(
+    Cartridge::from_bytes(vec![0; 0x100]),
+    Err(CartridgeError::TooSmall(0x100))
+  ));
+  assert!(matches!(
+    Cartridge::from_bytes(rom_with_header("", 0x04, 0x00, 0x00)),
+    Err(CartridgeError::UnknownCartridgeType(0x04))
+  ));
+
+  let mut rom = rom_with_header("", 0x00, 0x00, 0x00);
+  rom[0x149] = 0x09;
+  assert!(matches!(Cartridge::from_bytes(rom), Err(CartridgeError::InvalidRamSize(0x09))));
+}
+
+// Marks the first byte of every ROM bank with its bank number.
+fn banked_rom(cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+  let mut rom = rom_with_header("BANKS", cartridge_type, rom_size, ram_size);
+  for (bank, chunk) in rom.chunks_mut(0x4000).enumerate().skip(1) {
+    chunk[0] = bank as u8;
+  }
+  rom
+}
+
+#[test]
+fn mbc1_switches_rom_banks() {
+  // 2 MiB, 128 banks
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x01, 0x06, 0x00)).unwrap();
+  assert_eq!(cartridge.read(0x4000), 1);
+
+  cartridge.write(0x2000, 0x05);
+  assert_eq!(cartridge.read(0x4000), 5);
+
+  // Bank 0 maps bank 1.
+  cartridge.write(0x2000, 0x00);
+  assert_eq!(cartridge.read(0x4000), 1);
+
+  // BANK2 supplies bits 5-6.
+  cartridge.write(0x2000, 0x03);
+  cartridge.write(0x4000, 0x02);
+  assert_eq!(cartridge.read(0x4000), 0x43);
+}
+
+#[test]
+fn mbc1_cannot_map_banks_0x20_0x40_0x60_at_4000() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x01, 0x06, 0x00)).unwrap();
+
+  for bank2 in 1..=3 {
+    cartridge.write(0x4000, bank2);
+    cartridge.write(0x2000, 0x00);
+    assert_eq!(cartridge.read(0x4000), (bank2 << 5) + 1);
+  }
+}
+
+#[test]
+fn mbc1_mode_1_maps_bank2_at_0000() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x01, 0x06, 0x00)).unwrap();
+  cartridge.write(0x4000, 0x01);
+  assert_eq!(cartridge.read(0x0000), 0x00);
+
+  cartridge.write(0x6000, 0x01);
+  assert_eq!(cartridge.read(0x0000), 0x20);
+}
+
+#[test]
+fn mbc1_ram_enable_and_banking() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x03, 0x02, 0x03)).unwrap();
+
+  cartridge.write(0xA000, 0x42);
+  assert_eq!(cartridge.read(0xA000), 0xFF);
+
+  cartridge.write(0x0000, 0x0A);
+  cartridge.write(0xA000, 0x42);
+  assert_eq!(cartridge.read(0xA000), 0x42);
+
+  // RAM banking only applies in mode 1.
+  cartridge.write(0x4000, 0x02);
+  assert_eq!(cartridge.read(0xA000), 0x42);
+
+  cartridge.write(0x6000, 0x01);
+  assert_eq!(cartridge.read(0xA000), 0x00);
+  cartridge.write(0xA000, 0x24);
+  assert_eq!(cartridge.ram[2 * 0x2000], 0x24);
+
+  cartridge.write(0x0000, 0x00);
+  assert_eq!(cartridge.read(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc1m_multicart_uses_4_bit_bank1() {
+  // 1 MiB with a second logo at bank 0x10
+  let mut rom = banked_rom(0x01, 0x05, 0x00);
+  rom[0x104..0x134].copy_from_slice(&[0xCE; 48]);
+  rom[0x40104..0x40134].copy_from_slice(&[0xCE; 48]);
+
+  let mut cartridge = Cartridge::from_bytes(rom).unwrap();
+
+  cartridge.write(0x2000, 0x12);
+  cartridge.write(0x4000, 0x01);
+  assert_eq!(cartridge.read(0x4000), 0x12);
+
+  // In mode 1, 0000-3FFF maps the first bank of the second game.
+  cartridge.write(0x6000, 0x01);
+  assert_eq!(cartridge.read(0x0000), 0x10);
+}
+
+fn mbc3_with_clock() -> (Cartridge, ManualClock) {
+  let clock = ManualClock::default();
+  let rom = banked_rom(0x10, 0x06, 0x03);
+  let mut cartridge = Cartridge::from_bytes_with_clock(rom, Box::new(clock.clone())).unwrap();
+  cartridge.write(0x0000, 0x0A);
+  (cartridge, clock)
+}
+
+fn latch(cartridge: &mut Cartridge) {
+  cartridge.write(0x6000, 0x00);
+  cartridge.write(0x6000, 0x01);
+}
+
+fn read_rtc(cartridge: &mut Cartridge, select: u8) -> u8 {
+  cartridge.write(0x4000, select);
+  cartridge.read(0xA000)
+}
+
+#[test]
+fn mbc3_switches_rom_and_ram_banks() {
+  let (mut cartridge, _) = mbc3_with_clock();
+
+  cartridge.write(0x2000, 0x7F);
+  assert_eq!(cartridge.read(0x4000), 0x7F);
+  cartridge.write(0x2000, 0x00);
+  assert_eq!(cartridge.read(0x4000), 0x01);
+
+  cartridge.write(0x4000, 0x03);
+  cartridge.write(0xA000, 0x42);
+  assert_eq!(cartridge.ram[3 * 0x2000], 0x42);
+}
+
+#[test]
+fn mbc3_rtc_counts_from_the_clock_after_latch() {
+  let (mut cartridge, clock) = mbc3_with_clock();
+
+  clock.advance(90061); // 1 day, 1 hour, 1 minute, 1 second
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 0);
+
+  latch(&mut cartridge);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 1);
+  assert_eq!(read_rtc(&mut cartridge, 0x09), 1);
+  assert_eq!(read_rtc(&mut cartridge, 0x0A), 1);
+  assert_eq!(read_rtc(&mut cartridge, 0x0B), 1);
+  assert_eq!(read_rtc(&mut cartridge, 0x0C), 0);
+
+  // Latched values don't change until the next latch.
+  clock.advance(10);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 1);
+
+  // Latching needs a 0x00 write before 0x01.
+  cartridge.write(0x6000, 0x01);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 1);
+  latch(&mut cartridge);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 11);
+}
+
+#[test]
+fn mbc3_rtc_halt_stops_the_clock() {
+  let (mut cartridge, clock) = mbc3_with_clock();
+
+  cartridge.write(0x4000, 0x0C);
+  cartridge.write(0xA000, 0x40);
+  clock.advance(100);
+  latch(&mut cartridge);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 0);
+  assert_eq!(read_rtc(&mut cartridge, 0x0C), 0x40);
+
+  cartridge.write(0x4000, 0x0C);
+  cartridge.write(0xA000, 0x00);
+  clock.advance(5);
+  latch(&mut cartridge);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 5);
+}
+
+#[test]
+fn mbc3_rtc_day_counter_overflow_sets_carry() {
+  let (mut cartridge, clock) = mbc3_with_clock();
+
+  // Day 511, 23:59:59
+  cartridge.write(0x4000, 0x0B);
+  cartridge.write(0xA000, 0xFF);
+  cartridge.write(0x4000, 0x0C);
+  cartridge.write(0xA000, 0x01);
+  cartridge.write(0x4000, 0x0A);
+  cartridge.write(0xA000, 23);
+  cartridge.write(0x4000, 0x09);
+  cartridge.write(0xA000, 59);
+  cartridge.write(0x4000, 0x08);
+  cartridge.write(0xA000, 59);
+
+  clock.advance(1);
+  latch(&mut cartridge);
+  assert_eq!(read_rtc(&mut cartridge, 0x08), 0);
+  assert_eq!(read_rtc(&mut cartridge, 0x0A), 0);
+  assert_eq!(read_rtc(&mut cartridge, 0x0B), 0);
+  assert_eq!(read_rtc(&mut cartridge, 0x0C), 0x80);
+}
+
+#[test]
+fn mbc5_uses_9_bit_rom_bank() {
+  // 8 MiB, 512 banks
+  let mut rom = banked_rom(0x19, 0x08, 0x00);
+  rom[0x1FF * 0x4000 + 1] = 0xAA;
+  let mut cartridge = Cartridge::from_bytes(rom).unwrap();
+
+  // Bank 0 can be mapped at 4000-7FFF.
+  cartridge.write(0x2000, 0x00);
+  assert_eq!(cartridge.read(0x4000), 0x00);
+
+  cartridge.write(0x2000, 0xFF);
+  cartridge.write(0x3000, 0x01);
+  assert_eq!(cartridge.read(0x4001), 0xAA);
+
+  cartridge.write(0x3000, 0x00);
+  assert_eq!(cartridge.read(0x4000), 0xFF);
+}
+
+#[test]
+fn mbc5_switches_16_ram_banks() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x1B, 0x00, 0x04)).unwrap();
+  cartridge.write(0x0000, 0x0A);
+
+  cartridge.write(0x4000, 0x0F);
+  cartridge.write(0xA000, 0x42);
+  assert_eq!(cartridge.ram[15 * 0x2000], 0x42);
+  assert_eq!(cartridge.poll_event(), None);
+}
+
+#[test]
+fn mbc5_rumble_reports_motor_changes() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x1E, 0x00, 0x03)).unwrap();
+  cartridge.write(0x0000, 0x0A);
+
+  cartridge.write(0x4000, 0x08);
+  cartridge.write(0x4000, 0x0B);
+  cartridge.write(0x4000, 0x03);
+
+  assert_eq!(cartridge.poll_event(), Some(CartridgeEvent::Rumble(true)));
+  assert_eq!(cartridge.poll_event(), Some(CartridgeEvent::Rumble(false)));
+  assert_eq!(cartridge.poll_event(), None);
+
+  // Bit 3 is not part of the RAM bank number.
+  cartridge.write(0xA000, 0x42);
+  assert_eq!(cartridge.ram[3 * 0x2000], 0x42);
+}
+
+#[test]
+fn mbc2_selects_register_by_address_bit_8() {
+  // 256 KiB, 16 banks
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x06, 0x03, 0x00)).unwrap();
+
+  // Bit 8 clear: RAM enable, ROM bank unchanged.
+  cartridge.write(0x2000, 0x05);
+  assert_eq!(cartridge.read(0x4000), 1);
+
+  cartridge.write(0x2100, 0x05);
+  assert_eq!(cartridge.read(0x4000), 5);
+
+  cartridge.write(0x0100, 0x00);
+  assert_eq!(cartridge.read(0x4000), 1);
+
+  // ROM writes don't corrupt the image.
+  assert_eq!(cartridge.rom[0x0100], 0x00);
+}
+
+#[test]
+fn mbc2_ram_is_512_half_bytes_echoed_through_a000_bfff() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x06, 0x03, 0x00)).unwrap();
+  assert_eq!(cartridge.ram.len(), 512);
+
+  cartridge.write(0xA000, 0x05);
+  assert_eq!(cartridge.read(0xA000), 0xFF);
+
+  cartridge.write(0x0000, 0x0A);
+  cartridge.write(0xA000, 0x35);
+  assert_eq!(cartridge.read(0xA000), 0xF5);
+  assert_eq!(cartridge.read(0xA200), 0xF5);
+  assert_eq!(cartridge.read(0xBE00), 0xF5);
+
+  cartridge.write(0xBFFF, 0x0C);
+  assert_eq!(cartridge.read(0xA1FF), 0xFC);
+}
+
+#[test]
+fn battery_ram_round_trips_through_save_file() {
+  let path = std::env::temp_dir().join(format!("gb-save-test-{}.sav", std::process::id()));
+
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x03, 0x00, 0x02)).unwrap();
+  cartridge.write(0x0000, 0x0A);
+
+  // Nothing to write until the RAM changes.
+  cartridge.flush_save(&path).unwrap();
+  assert!(!path.exists());
+
+  cartridge.write(0xA123, 0x42);
+  cartridge.flush_save(&path).unwrap();
+  assert_eq!(std::fs::read(&path).unwrap().len(), 0x2000);
+
+  let mut loaded = Cartridge::from_bytes(banked_rom(0x03, 0x00, 0x02)).unwrap();
+  loaded.load_save(&path).unwrap();
+  loaded.write(0x0000, 0x0A);
+  assert_eq!(loaded.read(0xA123), 0x42);
+
+  std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn only_stored_ram_writes_mark_the_save_dirty() {
+  let mut cartridge = Cartridge::from_bytes(banked_rom(0x03, 0x00, 0x02)).unwrap();
+
+  cartridge.write(0xA000, 0x42);
+  assert!(!cartridge.ram_dirty);
+
+  cartridge.write(0x0000, 0x0A);
+  cartridge.write(0xA000, 0x42);
+  assert!(cartridge.ram_dirty);
+}
+
+#[test]
+fn mbc3_rtc_writes_do_not_mark_the_save_dirty() {
+  let (mut cartridge, _) = mbc3_with_clock();
+
+  cartridge.write(0x4000, 0x08);
+  cartridge.write(0xA000, 30);
+  assert!(!cartridge.ram_dirty);
+
+  cartridge.write(0x4000, 0x00);
+  cartridge.write(0xA000, 30);
+  assert!(cartridge.ram_dirty);
+}
+
+#[test]
+fn mbc3_save_appends_48_byte_rtc_trailer() {
+  let (mut cartridge, clock) = mbc3_with_clock();
+  clock.advance(1_700_000_000);
+  cartridge.write(0x4000, 0x09);
+  cartridge.write(0xA000, 30);
+  latch(&mut cartridge);
+
+  let data = cartridge.save_data();
+  assert_eq!(data.len(), 0x8000 + 48);
+
+  let trailer = &data[0x8000..];
+  assert_eq!(trailer[4..8], 30u32.to_le_bytes());
+  assert_eq!(trailer[24..28], 30u32.to_le_bytes());
+  assert_eq!(trailer[40..48], 1_700_000_000u64.to_le_bytes());
+
+  // The clock keeps running while the emulator is closed.
+  clock.advance(120);
+  let mut loaded =
+    Cartridge::from_bytes_with_clock(banked_rom(0x10, 0x06, 0x03), Box::new(clock.clone()))
+      .unwrap();
+  loaded.load_save_data(&data);
+  loaded.write(0x0000, 0x0A);
+  latch(&mut loaded);
+  assert_eq!(read_rtc(&mut loaded, 0x09), 32);
+}
@@ -0,0 +1,85 @@
+// Hand written vectors for the CB prefixed shifts, rotates and bit
+// operations.
+use serde_json::{json, Value};
+
+use super::regression::{case, run, state};
+
+// CB `opcode` at 0x0100, changing register `reg` from `before` to `after`
+// and F from `f_before` to `f_after`.
+fn register_case(opcode: u8, reg: &str, before: (u64, u64), after: (u64, u64)) -> Value {
+  let ram = json!([[0x0100, 0xCB], [0x0101, opcode]]);
+  case(
+    &format!("cb {:02x}", opcode),
+    state(0x0100, &[(reg, before.0), ("f", before.1)], ram.clone()),
+    state(0x0102, &[(reg, after.0), ("f", after.1)], ram),
+    2,
+  )
+}
+
+// CB `opcode` changing (HL) = 0xC000 from `before` to `after`.
+fn hl_case(opcode: u8, before: u64, after: u64, f: (u64, u64)) -> Value {
+  let code = [[0x0100, 0xCB], [0x0101, opcode as u64]];
+  let initial = json!([code[0], code[1], [0xC000, before]]);
+  let last = json!([code[0], code[1], [0xC000, after]]);
+  case(
+    &format!("cb {:02x}", opcode),
+    state(0x0100, &[("h", 0xC0), ("f", f.0)], initial),
+    state(0x0102, &[("h", 0xC0), ("f", f.1)], last),
+    4,
+  )
+}
+
+#[test]
+fn rlc_and_rrc_rotate_through_bit_0_and_7() {
+  run(register_case(0x00, "b", (0x85, 0x00), (0x0B, 0x10)));
+  run(register_case(0x00, "b", (0x00, 0x10), (0x00, 0x80)));
+  run(register_case(0x09, "c", (0x01, 0x00), (0x80, 0x10)));
+  run(register_case(0x09, "c", (0x02, 0x10), (0x01, 0x00)));
+}
+
+#[test]
+fn rl_and_rr_rotate_through_carry() {
+  run(register_case(0x12, "d", (0x80, 0x10), (0x01, 0x10)));
+  run(register_case(0x12, "d", (0x80, 0x00), (0x00, 0x90)));
+  run(register_case(0x1B, "e", (0x01, 0x10), (0x80, 0x10)));
+  run(register_case(0x1B, "e", (0x00, 0x10), (0x80, 0x00)));
+}
+
+#[test]
+fn sla_shifts_in_zero() {
+  run(register_case(0x24, "h", (0xC1, 0x00), (0x82, 0x10)));
+  run(register_case(0x24, "h", (0x80, 0x00), (0x00, 0x90)));
+}
+
+#[test]
+fn sra_keeps_bit_7() {
+  run(register_case(0x2D, "l", (0x81, 0x00), (0xC0, 0x10)));
+  run(register_case(0x2D, "l", (0x42, 0x10), (0x21, 0x00)));
+}
+
+#[test]
+fn srl_clears_bit_7() {
+  run(register_case(0x3F, "a", (0x81, 0x00), (0x40, 0x10)));
+  run(register_case(0x3F, "a", (0x01, 0x00), (0x00, 0x90)));
+}
+
+#[test]
+fn swap_exchanges_nibbles_and_clears_carry() {
+  run(register_case(0x37, "a", (0xF1, 0x70), (0x1F, 0x00)));
+  run(register_case(0x37, "a", (0x00, 0x10), (0x00, 0x80)));
+}
+
+#[test]
+fn shifts_on_hl_take_four_cycles() {
+  run(hl_case(0x06, 0x80, 0x01, (0x00, 0x10)));
+  run(hl_case(0x36, 0xAB, 0xBA, (0x10, 0x00)));
+}
+
+#[test]
+fn res_and_set() {
+  run(register_case(0xC0, "b", (0x00, 0xF0), (0x01, 0xF0)));
+  run(register_case(0xBF, "a", (0xFF, 0x00), (0x7F, 0x00)));
+
+  run(hl_case(0xBE, 0xFF, 0x7F, (0xF0, 0xF0)));
+  run(hl_case(0xDE, 0x00, 0x08, (0x00, 0x00)));
+}
@@ -0,0 +1,55 @@
+use super::cpu_with_program;
+use crate::cpu::{CpuError, ILLEGAL_OPCODES};
+use crate::interrupts::Interrupt;
+
+#[test]
+fn illegal_opcodes_report_opcode_and_pc() {
+  for opcode in ILLEGAL_OPCODES {
+    // NOP, illegal
+    let mut cpu = cpu_with_program(&[0x00, opcode]);
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.step(), Err(CpuError::IllegalOpcode { opcode, pc: 0x0101 }));
+    assert!(cpu.locked);
+  }
+}
+
+#[test]
+fn locked_cpu_ignores_interrupts() {
+  // EI, NOP, illegal
+  let mut cpu = cpu_with_program(&[0xFB, 0x00, 0xD3]);
+  cpu.bus.interrupts.enable = Interrupt::VBlank.bit();
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  assert!(cpu.step().is_err());
+
+  cpu.bus.interrupts.request(Interrupt::VBlank);
+  for _ in 0..10 {
+    assert_eq!(cpu.step(), Ok(()));
+    assert_eq!(cpu.cycles, 4);
+  }
+  assert_eq!(cpu.reg.pc, 0x0103);
+  assert_eq!(cpu.reg.sp, 0xFFFE);
+}
+
+#[test]
+fn hardware_keeps_running_while_locked() {
+  let mut cpu = cpu_with_program(&[0xFD]);
+  assert!(cpu.step().is_err());
+
+  let divider = cpu.bus.timer.divider;
+  for _ in 0..64 {
+    cpu.step().unwrap();
+  }
+  assert_eq!(cpu.bus.timer.divider, divider.wrapping_add(256));
+}
+
+#[test]
+fn error_messages() {
+  let illegal = CpuError::IllegalOpcode {
+    opcode: 0xED,
+    pc: 0x1234,
+  };
+  assert_eq!(illegal.to_string(), "illegal opcode ED at 1234, cpu locked up");
+  assert_eq!(illegal.pc(), 0x1234);
+}
@@ -0,0 +1,82 @@
+use super::cpu_with_program;
+use crate::boot;
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::dma::{DMA_ADDR, DMA_LENGTH};
+use crate::memory::MemoryInterface;
+
+// Bus with 0xC000-0xC09F holding 0x00-0x9F.
+fn bus_with_source() -> Bus {
+  let mut bus = Bus::new();
+  for i in 0..DMA_LENGTH as u16 {
+    bus.write(0xC000 + i, i as u8);
+  }
+  bus
+}
+
+#[test]
+fn copies_160_bytes_into_oam() {
+  let mut bus = bus_with_source();
+  bus.write(DMA_ADDR, 0xC0);
+  assert_eq!(bus.read(DMA_ADDR), 0xC0);
+
+  bus.tick(DMA_LENGTH as usize * 4);
+
+  assert!(!bus.dma.active());
+  let expected: Vec<u8> = (0..DMA_LENGTH).collect();
+  assert_eq!(&bus.ppu.oam[..], &expected[..]);
+  assert_eq!(bus.read(0xFE9F), 0x9F);
+}
+
+#[test]
+fn copies_one_byte_per_m_cycle() {
+  let mut bus = bus_with_source();
+  bus.ppu.oam = [0xEE; 0xA0];
+  bus.write(DMA_ADDR, 0xC0);
+
+  bus.tick(40);
+  assert_eq!(bus.ppu.oam[9], 0x09);
+  assert_eq!(bus.ppu.oam[10], 0xEE);
+  assert!(bus.dma.active());
+}
+
+#[test]
+fn oam_is_blocked_during_transfer() {
+  let mut bus = bus_with_source();
+  bus.write(DMA_ADDR, 0xC0);
+  bus.tick(4);
+
+  assert_eq!(bus.read(0xFE00), 0xFF);
+  bus.write(0xFE50, 0x12);
+  bus.tick(DMA_LENGTH as usize * 4);
+  assert_eq!(bus.read(0xFE50), 0x50);
+}
+
+#[test]
+fn sources_above_dfff_read_work_ram() {
+  let mut bus = Bus::new();
+  bus.write(0xD100, 0x42);
+  bus.write(DMA_ADDR, 0xF1);
+  bus.tick(DMA_LENGTH as usize * 4);
+  assert_eq!(bus.ppu.oam[0], 0x42);
+}
+
+#[test]
+fn skip_boot_does_not_start_a_transfer() {
+  let mut cpu = Cpu::new();
+  boot::skip_boot(&mut cpu);
+  assert_eq!(cpu.bus.read(DMA_ADDR), 0xFF);
+  assert!(!cpu.bus.dma.active());
+}
+
+#[test]
+fn ldh_starts_transfer() {
+  // LD A, 0xC0; LDH (0x46), A
+  let mut cpu = cpu_with_program(&[0x3E, 0xC0, 0xE0, 0x46]);
+  cpu.bus.write(0xC005, 0x77);
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  cpu.bus.tick(DMA_LENGTH as usize * 4);
+  assert_eq!(cpu.bus.ppu.oam[5], 0x77);
+}
@@ -0,0 +1,89 @@
+use crate::cartridge::Cartridge;
+use crate::gameboy::{GameBoy, CYCLES_PER_FRAME};
+use crate::memory::MemoryInterface;
+use crate::model::Model;
+use crate::ppu::{Mode, LCDC_ADDR};
+use crate::tests::cartridge::rom_with_header;
+
+// Cartridge looping on `JR -2` (12 T-cycles) at the entry point.
+fn looping_gameboy() -> GameBoy {
+  let mut rom = rom_with_header("LOOP", 0x00, 0x00, 0x00);
+  rom[0x0100..0x0102].copy_from_slice(&[0x18, 0xFE]);
+  GameBoy::new(Cartridge::from_bytes(rom).unwrap(), Model::Dmg, None)
+}
+
+#[test]
+fn starts_at_entry_point_without_boot_rom() {
+  let gameboy = looping_gameboy();
+  assert_eq!(gameboy.cpu.reg.pc, 0x0100);
+  assert_eq!(gameboy.cpu.reg.a, 0x01);
+  assert_eq!(gameboy.cartridge().header.title, "LOOP");
+}
+
+#[test]
+fn step_instruction_returns_cycles() {
+  let mut gameboy = looping_gameboy();
+  assert_eq!(gameboy.step_instruction(), Ok(12));
+  assert_eq!(gameboy.cpu.reg.pc, 0x0100);
+}
+
+#[test]
+fn run_cycles_runs_whole_instructions() {
+  let mut gameboy = looping_gameboy();
+  assert_eq!(gameboy.run_cycles(100), Ok(108));
+  assert_eq!(gameboy.run_cycles(0), Ok(0));
+}
+
+#[test]
+fn run_frame_ends_when_vblank_starts() {
+  let mut gameboy = looping_gameboy();
+
+  gameboy.run_frame().unwrap();
+  assert_eq!(gameboy.bus().ppu.ly, 144);
+  assert_eq!(gameboy.bus().ppu.mode, Mode::VBlank);
+
+  // A frame later, to within one instruction.
+  let div = gameboy.bus().timer.divider;
+  gameboy.run_frame().unwrap();
+  let elapsed = gameboy.bus().timer.divider.wrapping_sub(div) as usize;
+  let frame = CYCLES_PER_FRAME % 0x10000;
+  assert!((frame..frame + 12).contains(&elapsed), "{}", elapsed);
+  assert_eq!(gameboy.bus().ppu.ly, 144);
+}
+
+#[test]
+fn run_frame_resyncs_after_the_lcd_is_turned_back_on() {
+  let mut gameboy = looping_gameboy();
+  gameboy.run_cycles(30_000).unwrap();
+
+  gameboy.bus_mut().write(LCDC_ADDR, 0x11);
+  gameboy.run_cycles(1_000).unwrap();
+  gameboy.bus_mut().write(LCDC_ADDR, 0x91);
+
+  gameboy.run_frame().unwrap();
+  assert_eq!(gameboy.bus().ppu.ly, 144);
+  assert_eq!(gameboy.bus().ppu.mode, Mode::VBlank);
+}
+
+#[test]
+fn run_frame_carries_overshoot_into_next_frame_with_lcd_off() {
+  // NOP; LD A, (0xC000); LD A, 0x00; JR -8: 4 + 16 + 8 + 12 T-cycles, so
+  // frames don't end on instruction boundaries.
+  let mut rom = rom_with_header("LOOP", 0x00, 0x00, 0x00);
+  rom[0x0150..0x0158].copy_from_slice(&[0x00, 0xFA, 0x00, 0xC0, 0x3E, 0x00, 0x18, 0xF8]);
+  let mut gameboy = GameBoy::new(Cartridge::from_bytes(rom).unwrap(), Model::Dmg, None);
+  gameboy.cpu.reg.pc = 0x0150;
+  gameboy.bus_mut().write(LCDC_ADDR, 0x00);
+  let div = gameboy.bus().timer.divider;
+
+  // 1755 loops, then the LD A, n crosses the boundary by 4.
+  gameboy.run_frame().unwrap();
+  let elapsed = gameboy.bus().timer.divider.wrapping_sub(div) as usize;
+  assert_eq!(elapsed, (CYCLES_PER_FRAME + 4) % 0x10000);
+  assert_eq!(gameboy.cpu.reg.pc, 0x0156);
+
+  // The next frame starts 4 T-cycles in and ends 12 past its boundary.
+  gameboy.run_frame().unwrap();
+  let elapsed = gameboy.bus().timer.divider.wrapping_sub(div) as usize;
+  assert_eq!(elapsed, (2 * CYCLES_PER_FRAME + 12) % 0x10000);
+}
@@ -0,0 +1,120 @@
+use super::cpu_with_program;
+use crate::interrupts::Interrupt;
+use crate::memory::MemoryInterface;
+
+#[test]
+fn dispatches_highest_priority_interrupt() {
+  let mut cpu = cpu_with_program(&[0x00]);
+  cpu.ime = true;
+  cpu.bus.interrupts.enable = 0x1F;
+  cpu.bus.interrupts.request(Interrupt::Timer);
+  cpu.bus.interrupts.request(Interrupt::LcdStat);
+
+  cpu.step().unwrap();
+
+  assert_eq!(cpu.reg.pc, 0x48);
+  assert_eq!(cpu.cycles, 20);
+  assert!(!cpu.ime);
+  assert_eq!(cpu.bus.interrupts.flag, Interrupt::Timer.bit());
+  assert_eq!(cpu.pop(), 0x0100);
+}
+
+#[test]
+fn ei_enables_interrupts_after_next_instruction() {
+  // EI, NOP, NOP
+  let mut cpu = cpu_with_program(&[0xFB, 0x00, 0x00]);
+  cpu.bus.interrupts.enable = Interrupt::VBlank.bit();
+  cpu.bus.interrupts.request(Interrupt::VBlank);
+
+  cpu.step().unwrap();
+  assert!(!cpu.ime);
+
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.pc, 0x0102);
+  assert!(cpu.ime);
+
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.pc, 0x40);
+}
+
+#[test]
+fn ei_followed_by_di_keeps_interrupts_disabled() {
+  // EI, DI, NOP
+  let mut cpu = cpu_with_program(&[0xFB, 0xF3, 0x00]);
+  cpu.bus.interrupts.enable = Interrupt::VBlank.bit();
+  cpu.bus.interrupts.request(Interrupt::VBlank);
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+
+  assert!(!cpu.ime);
+  assert_eq!(cpu.reg.pc, 0x0103);
+}
+
+#[test]
+fn halt_waits_for_interrupt() {
+  // HALT, INC A
+  let mut cpu = cpu_with_program(&[0x76, 0x3C]);
+  cpu.bus.interrupts.enable = Interrupt::Timer.bit();
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  assert!(cpu.halted);
+  assert_eq!(cpu.reg.pc, 0x0101);
+
+  // With IME=0 the CPU resumes without dispatching.
+  cpu.bus.interrupts.request(Interrupt::Timer);
+  cpu.step().unwrap();
+  assert!(!cpu.halted);
+  assert_eq!(cpu.reg.a, 1);
+}
+
+#[test]
+fn halt_bug_executes_next_byte_twice() {
+  // HALT, INC A
+  let mut cpu = cpu_with_program(&[0x76, 0x3C]);
+  cpu.bus.interrupts.enable = Interrupt::Timer.bit();
+  cpu.bus.interrupts.request(Interrupt::Timer);
+
+  cpu.step().unwrap();
+  assert!(!cpu.halted);
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.a, 2);
+  assert_eq!(cpu.reg.pc, 0x0102);
+}
+
+#[test]
+fn ei_halt_with_pending_interrupt_returns_to_halt() {
+  // EI, HALT, INC B
+  let mut cpu = cpu_with_program(&[0xFB, 0x76, 0x04]);
+  // INC A, RETI
+  cpu.bus.poke(0x0050, 0x3C);
+  cpu.bus.poke(0x0051, 0xD9);
+  cpu.bus.interrupts.enable = Interrupt::Timer.bit();
+  cpu.bus.interrupts.request(Interrupt::Timer);
+
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  assert!(cpu.halt_bug);
+
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.pc, 0x0050);
+  assert!(!cpu.halt_bug);
+  assert_eq!(cpu.pop(), 0x0101);
+  cpu.push(0x0101);
+
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.a, 1);
+  assert_eq!(cpu.reg.pc, 0x0051);
+
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.pc, 0x0101);
+
+  // The HALT runs again and waits for the next interrupt.
+  cpu.step().unwrap();
+  assert!(cpu.halted);
+  assert_eq!(cpu.reg.b, 0);
+}
@@ -0,0 +1,33 @@
+// Runs the SM83 single-step JSON vectors (https://github.com/SingleStepTests/sm83)
+// found in `roms/json_tests`, or in the directory given by `GB_JSON_TESTS`.
+// The vectors are not part of the repository, so the test is ignored by
+// default. To run it:
+//
+//   cargo test single_step_json_vectors -- --ignored
+use std::env;
+use std::path::PathBuf;
+
+use super::TestSuite;
+use crate::memory::FlatRam;
+
+#[test]
+#[ignore = "needs roms/json_tests"]
+fn single_step_json_vectors() {
+  let dir = env::var("GB_JSON_TESTS")
+    .map(PathBuf::from)
+    .unwrap_or_else(|_| PathBuf::from("./roms/json_tests"));
+
+  assert!(dir.is_dir(), "{} not found", dir.display());
+
+  let mut test = TestSuite::<FlatRam>::new();
+  let report = test.run_dir(&dir).unwrap();
+  report.print();
+
+  for (opcode, result) in &report.opcodes {
+    for failure in result.failures.iter().take(3) {
+      println!("{}: {}", opcode, failure);
+    }
+  }
+
+  assert_eq!(report.failed(), 0);
+}
@@ -1,62 +1,266 @@
 use crate::cpu::Cpu;
-use serde::{Deserialize, Serialize};
+use crate::memory::{FlatRam, MemoryInterface};
 use serde_json::{Result, Value};
-use std::{file, fs};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct MemoryValue {
-  address: u16,
-  value: u8,
+#[cfg(test)]
+mod acid2;
+#[cfg(test)]
+mod apu;
+#[cfg(test)]
+mod archive;
+#[cfg(test)]
+mod boot;
+#[cfg(test)]
+mod bus;
+#[cfg(test)]
+mod cartridge;
+#[cfg(test)]
+mod cb_opcodes;
+#[cfg(test)]
+mod cpu_errors;
+#[cfg(test)]
+mod dma;
+#[cfg(test)]
+mod gameboy;
+#[cfg(test)]
+mod interrupts;
+#[cfg(test)]
+mod json_tests;
+#[cfg(test)]
+mod model;
+#[cfg(test)]
+mod opcodes;
+#[cfg(test)]
+mod patch;
+#[cfg(test)]
+mod ppu;
+#[cfg(test)]
+mod regression;
+#[cfg(test)]
+mod timer;
+
+// CPU on a default bus with `program` at the 0x0100 entry point, PC on its
+// first byte and SP at 0xFFFE.
+pub fn cpu_with_program(program: &[u8]) -> Cpu {
+  let mut cpu = Cpu::new();
+  for (i, &data) in program.iter().enumerate() {
+    cpu.bus.poke(0x0100 + i as u16, data);
+  }
+
+  cpu.reg.pc = 0x0100;
+  cpu.reg.sp = 0xFFFE;
+  cpu
 }
 
-pub struct TestSuite {
-  cpu: *mut Cpu,
-  memory: *mut [u8; 0xffff],
+#[derive(Default, Debug)]
+pub struct OpcodeReport {
+  pub passed: usize,
+  pub failed: usize,
+  pub failures: Vec<String>,
 }
 
-impl TestSuite {
-  pub fn new(cpu: &mut Cpu, memory: &mut [u8; 0xffff]) -> TestSuite {
-    TestSuite { cpu, memory }
+// Pass/fail counts keyed by opcode, e.g. "20" or "cb 7c".
+#[derive(Default, Debug)]
+pub struct TestReport {
+  pub opcodes: BTreeMap<String, OpcodeReport>,
+}
+
+impl TestReport {
+  pub fn passed(&self) -> usize {
+    self.opcodes.values().map(|r| r.passed).sum()
   }
 
-  pub fn set_cpu_state(&mut self, cpu_state: Value) {
-    unsafe {
-      (*self.cpu).reg.a = cpu_state["a"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.b = cpu_state["b"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.c = cpu_state["c"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.d = cpu_state["d"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.e = cpu_state["e"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.f = cpu_state["f"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.h = cpu_state["h"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.l = cpu_state["l"].as_u64().unwrap() as u8;
-      (*self.cpu).reg.pc = cpu_state["pc"].as_u64().unwrap() as u16;
-      (*self.cpu).reg.sp = cpu_state["sp"].as_u64().unwrap() as u16;
+  pub fn failed(&self) -> usize {
+    self.opcodes.values().map(|r| r.failed).sum()
+  }
 
-      (*self.cpu).debug();
+  pub fn merge(&mut self, other: TestReport) {
+    for (opcode, report) in other.opcodes {
+      let entry = self.opcodes.entry(opcode).or_default();
+      entry.passed += report.passed;
+      entry.failed += report.failed;
+      entry.failures.extend(report.failures);
     }
   }
 
-  pub fn set_ram_state(&mut self, ram: Value) {
-    println!("ram: {}", ram);
+  pub fn print(&self) {
+    for (opcode, report) in &self.opcodes {
+      let status = if report.failed == 0 { "PASS" } else { "FAIL" };
+      println!(
+        "{} {:<6} {:>5} passed {:>5} failed",
+        status, opcode, report.passed, report.failed
+      );
+    }
+    println!("total: {} passed, {} failed", self.passed(), self.failed());
+  }
+}
+
+pub struct TestSuite<B: MemoryInterface = FlatRam> {
+  pub cpu: Cpu<B>,
+}
 
-    //   if let Some(array) = ram.as_array() {
-    //     for item in array.iter() {
-    //         if let Some(addr) = item.get(0) {
-    //             if let Some(data) = item.get(1) {
-    //                 println!("addr: {:?}, data: {:?}", addr, data);
-    //               self.memory.wrapping_add(addr.as_u64().unwrap() as usize) = data.as_u64().unwrap() as u8;
-    //             }
-    //         }
-    //     }
-    // }
+impl<B: MemoryInterface + Default> TestSuite<B> {
+  pub fn new() -> TestSuite<B> {
+    TestSuite {
+      cpu: Cpu::with_bus(B::default()),
+    }
   }
 
-  pub fn run_test(&mut self, path_to_test: &str) -> Result<()> {
+  pub fn set_cpu_state(&mut self, cpu_state: &Value) {
+    self.cpu.reg.a = cpu_state["a"].as_u64().unwrap() as u8;
+    self.cpu.reg.b = cpu_state["b"].as_u64().unwrap() as u8;
+    self.cpu.reg.c = cpu_state["c"].as_u64().unwrap() as u8;
+    self.cpu.reg.d = cpu_state["d"].as_u64().unwrap() as u8;
+    self.cpu.reg.e = cpu_state["e"].as_u64().unwrap() as u8;
+    self.cpu.reg.f = cpu_state["f"].as_u64().unwrap() as u8;
+    self.cpu.reg.h = cpu_state["h"].as_u64().unwrap() as u8;
+    self.cpu.reg.l = cpu_state["l"].as_u64().unwrap() as u8;
+    self.cpu.reg.pc = cpu_state["pc"].as_u64().unwrap() as u16;
+    self.cpu.reg.sp = cpu_state["sp"].as_u64().unwrap() as u16;
+    self.cpu.ime = cpu_state["ime"].as_u64().unwrap_or(0) == 1;
+    self.cpu.ime_scheduled = false;
+    self.cpu.halted = false;
+    self.cpu.halt_bug = false;
+    self.cpu.stopped = false;
+  }
+
+  pub fn set_ram_state(&mut self, ram: &Value) {
+    for item in ram.as_array().unwrap() {
+      let addr = item[0].as_u64().unwrap() as u16;
+      let data = item[1].as_u64().unwrap() as u8;
+      self.cpu.bus.poke(addr, data);
+    }
+  }
+
+  // Returns one message per register that differs from the expected state.
+  pub fn check_cpu_state(&self, cpu_state: &Value) -> Vec<String> {
+    let cpu = &self.cpu;
+    let registers = [
+      ("a", cpu.reg.a as u64),
+      ("b", cpu.reg.b as u64),
+      ("c", cpu.reg.c as u64),
+      ("d", cpu.reg.d as u64),
+      ("e", cpu.reg.e as u64),
+      ("f", cpu.reg.f as u64),
+      ("h", cpu.reg.h as u64),
+      ("l", cpu.reg.l as u64),
+      ("pc", cpu.reg.pc as u64),
+      ("sp", cpu.reg.sp as u64),
+      ("ime", cpu.ime as u64),
+    ];
+
+    registers
+      .iter()
+      .filter_map(|&(name, actual)| {
+        let expected = cpu_state[name].as_u64()?;
+        (expected != actual)
+          .then(|| format!("{}: expected {:02X}, got {:02X}", name, expected, actual))
+      })
+      .collect()
+  }
+
+  // Returns one message per memory address that differs from the expected state.
+  pub fn check_ram_state(&self, ram: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for item in ram.as_array().unwrap() {
+      let addr = item[0].as_u64().unwrap() as u16;
+      let expected = item[1].as_u64().unwrap() as u8;
+      let actual = self.cpu.bus.read(addr);
+
+      if expected != actual {
+        errors.push(format!("ram[{:04X}]: expected {:02X}, got {:02X}", addr, expected, actual));
+      }
+    }
+
+    errors
+  }
+
+  // Runs a single test case: loads the `initial` state, steps the CPU once and
+  // compares the result with the `final` state. Each entry of `cycles` is one M-cycle.
+  pub fn run_case(&mut self, case: &Value) -> std::result::Result<(), String> {
+    // A fresh CPU and bus, so nothing left by the previous case (like IE and
+    // IF) leaks into this one.
+    self.cpu = Cpu::with_bus(B::default());
+    self.set_cpu_state(&case["initial"]);
+    self.set_ram_state(&case["initial"]["ram"]);
+
+    let step = self.cpu.step();
+
+    let mut errors = Vec::new();
+    if let Err(e) = step {
+      errors.push(e.to_string());
+    }
+
+    errors.extend(self.check_cpu_state(&case["final"]));
+    errors.extend(self.check_ram_state(&case["final"]["ram"]));
+
+    let expected_cycles = case["cycles"].as_array().unwrap().len() * 4;
+    let actual_cycles = self.cpu.cycles;
+    if expected_cycles != actual_cycles {
+      errors.push(format!("cycles: expected {}, got {}", expected_cycles, actual_cycles));
+    }
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(format!("{}: {}", case["name"].as_str().unwrap_or("?"), errors.join(", ")))
+    }
+  }
+
+  // Runs every case of a test file and counts the results per opcode.
+  pub fn run_test(&mut self, path_to_test: &str) -> Result<TestReport> {
     let test_file = fs::read_to_string(path_to_test).expect("Error: Can't read test file.");
-    let json_test: Value = serde_json::from_str(&test_file).unwrap();
-    self.set_cpu_state(json_test[0]["initial"].clone());
-    self.set_ram_state(json_test[0]["initial"]["ram"].clone());
+    let json_test: Value = serde_json::from_str(&test_file)?;
+
+    let mut report = TestReport::default();
 
-    Ok(())
+    for case in json_test.as_array().into_iter().flatten() {
+      let name = case["name"].as_str().unwrap_or("?");
+      let opcode = opcode_of(name);
+      let entry = report.opcodes.entry(opcode).or_default();
+
+      match self.run_case(case) {
+        Ok(_) => entry.passed += 1,
+        Err(e) => {
+          entry.failed += 1;
+          entry.failures.push(e);
+        }
+      }
+    }
+
+    Ok(report)
+  }
+
+  // Runs every `.json` file of a directory, in file name order.
+  pub fn run_dir(&mut self, path_to_dir: &Path) -> Result<TestReport> {
+    let mut paths: Vec<_> = fs::read_dir(path_to_dir)
+      .expect("Error: Can't read test directory.")
+      .filter_map(|entry| entry.ok().map(|e| e.path()))
+      .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+      .collect();
+    paths.sort();
+
+    let mut report = TestReport::default();
+    for path in paths {
+      report.merge(self.run_test(path.to_str().unwrap())?);
+    }
+
+    Ok(report)
+  }
+}
+
+// Test names are "<opcode> <index>", with "cb <opcode>" for prefixed instructions.
+fn opcode_of(name: &str) -> String {
+  let mut parts = name.split_whitespace();
+  match parts.next() {
+    Some(prefix) if prefix.eq_ignore_ascii_case("cb") => {
+      format!("cb {}", parts.next().unwrap_or("??")).to_lowercase()
+    }
+    Some(opcode) => opcode.to_lowercase(),
+    None => "??".to_string(),
   }
 }
@@ -0,0 +1,94 @@
+use crate::boot;
+use crate::bus::Bus;
+use crate::cartridge::{Cartridge, CartridgeHeader};
+use crate::cpu::Cpu;
+use crate::memory::MemoryInterface;
+use crate::model::Model;
+use crate::tests::cartridge::rom_with_header;
+
+fn header(cgb_flag: u8) -> CartridgeHeader {
+  let mut rom = rom_with_header("MODEL", 0x00, 0x00, 0x00);
+  rom[0x143] = cgb_flag;
+  CartridgeHeader::parse(&rom).unwrap()
+}
+
+#[test]
+fn detects_model_from_cgb_flag() {
+  assert_eq!(Model::detect(&header(0x00)), Model::Dmg);
+  assert_eq!(Model::detect(&header(0x80)), Model::Cgb);
+  assert_eq!(Model::detect(&header(0xC0)), Model::Cgb);
+}
+
+#[test]
+fn parses_model_names() {
+  assert_eq!("dmg".parse(), Ok(Model::Dmg));
+  assert_eq!("MGB".parse(), Ok(Model::Mgb));
+  assert_eq!("sgb".parse(), Ok(Model::Sgb));
+  assert_eq!("cgb".parse(), Ok(Model::Cgb));
+  assert_eq!("agb".parse(), Ok(Model::Agb));
+  assert!("gba".parse::<Model>().is_err());
+}
+
+#[test]
+fn post_boot_registers_identify_the_model() {
+  let dmg = header(0x00);
+  let cgb = header(0x80);
+
+  assert_eq!(Model::Dmg.post_boot_registers(&dmg).a, 0x01);
+  assert_eq!(Model::Mgb.post_boot_registers(&dmg).a, 0xFF);
+  assert_eq!(Model::Sgb.post_boot_registers(&dmg).get_bc(), 0x0014);
+
+  let reg = Model::Cgb.post_boot_registers(&cgb);
+  assert_eq!((reg.get_af(), reg.get_bc(), reg.get_de()), (0x1180, 0x0000, 0xFF56));
+
+  let reg = Model::Agb.post_boot_registers(&cgb);
+  assert_eq!((reg.get_af(), reg.get_bc()), (0x1100, 0x0100));
+
+  // DMG cartridge in CGB compatibility mode.
+  let reg = Model::Cgb.post_boot_registers(&dmg);
+  assert_eq!((reg.a, reg.get_de(), reg.get_hl()), (0x11, 0x0008, 0x007C));
+}
+
+#[test]
+fn skip_boot_uses_bus_model() {
+  let mut rom = rom_with_header("MODEL", 0x00, 0x00, 0x00);
+  rom[0x143] = 0x80;
+
+  let mut cpu = Cpu::new();
+  cpu
+    .bus
+    .insert_cartridge(Cartridge::from_bytes(rom).unwrap());
+  cpu.bus.model = Model::Cgb;
+
+  boot::skip_boot(&mut cpu);
+
+  assert_eq!(cpu.reg.a, 0x11);
+  assert_eq!(cpu.reg.pc, 0x0100);
+  assert_eq!(cpu.bus.read(0xFF4D), 0x7E);
+  assert_eq!(cpu.bus.read(0xFF70), 0xF8);
+  assert_eq!(Model::Cgb.boot_rom_size(), 0x900);
+  assert_eq!(Model::Dmg.boot_rom_size(), 0x100);
+}
+
+#[test]
+fn cgb_registers_only_exist_on_cgb_models() {
+  let models = [
+    (Model::Dmg, false),
+    (Model::Mgb, false),
+    (Model::Sgb, false),
+    (Model::Cgb, true),
+    (Model::Agb, true),
+  ];
+
+  for (model, is_cgb) in models {
+    let mut bus = Bus::new();
+    bus.model = model;
+
+    // KEY1, VBK, RP, SVBK
+    for addr in [0xFF4D, 0xFF4F, 0xFF56, 0xFF70] {
+      bus.write(addr, 0x01);
+      let expected = if is_cgb { 0x01 } else { 0xFF };
+      assert_eq!(bus.read(addr), expected, "{} {:04X}", model, addr);
+    }
+  }
+}
@@ -0,0 +1,222 @@
+// Hand written vectors for unprefixed opcodes with tricky flags or timing,
+// so they're covered without the full JSON suite.
+use serde_json::json;
+
+use super::regression::{case, run, state};
+
+#[test]
+fn daa_adjusts_after_addition() {
+  let ram = json!([[0x0100, 0x27]]);
+  // 0x45 + 0x38
+  run(case(
+    "27 low nibble",
+    state(0x0100, &[("a", 0x7D)], ram.clone()),
+    state(0x0101, &[("a", 0x83)], ram.clone()),
+    1,
+  ));
+
+  // 0x99 + 0x67
+  run(case(
+    "27 half carry and carry",
+    state(0x0100, &[("a", 0x00), ("f", 0x30)], ram.clone()),
+    state(0x0101, &[("a", 0x66), ("f", 0x10)], ram.clone()),
+    1,
+  ));
+
+  run(case(
+    "27 overflow to zero",
+    state(0x0100, &[("a", 0x9A)], ram.clone()),
+    state(0x0101, &[("a", 0x00), ("f", 0x90)], ram),
+    1,
+  ));
+}
+
+#[test]
+fn daa_adjusts_after_subtraction() {
+  let ram = json!([[0x0100, 0x27]]);
+  // 0x10 - 0x01
+  run(case(
+    "27 half borrow",
+    state(0x0100, &[("a", 0x0F), ("f", 0x60)], ram.clone()),
+    state(0x0101, &[("a", 0x09), ("f", 0x40)], ram.clone()),
+    1,
+  ));
+
+  // 0x10 - 0x20
+  run(case(
+    "27 borrow",
+    state(0x0100, &[("a", 0xF0), ("f", 0x50)], ram.clone()),
+    state(0x0101, &[("a", 0x90), ("f", 0x50)], ram),
+    1,
+  ));
+}
+
+#[test]
+fn add_sp_e_flags_come_from_low_byte() {
+  let ram = json!([[0x0100, 0xE8], [0x0101, 0x08]]);
+  run(case(
+    "e8 carry",
+    state(0x0100, &[("sp", 0xFFF8)], ram.clone()),
+    state(0x0102, &[("sp", 0x0000), ("f", 0x30)], ram),
+    4,
+  ));
+
+  let ram = json!([[0x0100, 0xE8], [0x0101, 0xFF]]);
+  run(case(
+    "e8 negative",
+    state(0x0100, &[("sp", 0x1000), ("f", 0xC0)], ram.clone()),
+    state(0x0102, &[("sp", 0x0FFF), ("f", 0x00)], ram),
+    4,
+  ));
+}
+
+#[test]
+fn ld_hl_sp_e_leaves_sp_alone() {
+  let ram = json!([[0x0100, 0xF8], [0x0101, 0x01]]);
+  run(case(
+    "f8 half carry",
+    state(0x0100, &[("sp", 0x000F)], ram.clone()),
+    state(0x0102, &[("sp", 0x000F), ("h", 0x00), ("l", 0x10), ("f", 0x20)], ram),
+    3,
+  ));
+
+  let ram = json!([[0x0100, 0xF8], [0x0101, 0xFE]]);
+  run(case(
+    "f8 negative",
+    state(0x0100, &[("sp", 0xFFF8)], ram.clone()),
+    state(0x0102, &[("sp", 0xFFF8), ("h", 0xFF), ("l", 0xF6), ("f", 0x30)], ram),
+    3,
+  ));
+}
+
+#[test]
+fn pop_af_masks_low_nibble_of_f() {
+  let ram = json!([[0x0100, 0xF1], [0xFFFC, 0xFF], [0xFFFD, 0x12]]);
+  run(case(
+    "f1",
+    state(0x0100, &[("sp", 0xFFFC)], ram.clone()),
+    state(0x0101, &[("a", 0x12), ("f", 0xF0)], ram),
+    3,
+  ));
+}
+
+#[test]
+fn adc_and_sbc_include_carry_in_half_carry() {
+  let ram = json!([[0x0100, 0x88]]);
+  run(case(
+    "88 half carry from carry",
+    state(0x0100, &[("a", 0x0F), ("f", 0x10)], ram.clone()),
+    state(0x0101, &[("a", 0x10), ("f", 0x20)], ram.clone()),
+    1,
+  ));
+  run(case(
+    "88 wraps to zero",
+    state(0x0100, &[("a", 0xFF), ("f", 0x10)], ram.clone()),
+    state(0x0101, &[("a", 0x00), ("f", 0xB0)], ram),
+    1,
+  ));
+
+  let ram = json!([[0x0100, 0xCE], [0x0101, 0x0F]]);
+  run(case(
+    "ce",
+    state(0x0100, &[("a", 0x00), ("f", 0x10)], ram.clone()),
+    state(0x0102, &[("a", 0x10), ("f", 0x20)], ram),
+    2,
+  ));
+
+  let ram = json!([[0x0100, 0x98]]);
+  run(case(
+    "98 half borrow from carry",
+    state(0x0100, &[("a", 0x10), ("f", 0x10)], ram.clone()),
+    state(0x0101, &[("a", 0x0F), ("f", 0x60)], ram.clone()),
+    1,
+  ));
+  run(case(
+    "98 borrow",
+    state(0x0100, &[("a", 0x00), ("b", 0xFF), ("f", 0x10)], ram.clone()),
+    state(0x0101, &[("a", 0x00), ("b", 0xFF), ("f", 0xF0)], ram),
+    1,
+  ));
+
+  let ram = json!([[0x0100, 0xDE], [0x0101, 0x01]]);
+  run(case(
+    "de",
+    state(0x0100, &[("a", 0x02), ("f", 0x10)], ram.clone()),
+    state(0x0102, &[("a", 0x00), ("f", 0xC0)], ram),
+    2,
+  ));
+}
+
+#[test]
+fn conditional_call_cycles() {
+  let ram = json!([[0x0100, 0xC4], [0x0101, 0x34], [0x0102, 0x12]]);
+  let pushed = json!([
+    [0x0100, 0xC4],
+    [0x0101, 0x34],
+    [0x0102, 0x12],
+    [0xFFFC, 0x03],
+    [0xFFFD, 0x01]
+  ]);
+  run(case(
+    "c4 taken",
+    state(0x0100, &[], ram.clone()),
+    state(0x1234, &[("sp", 0xFFFC)], pushed),
+    6,
+  ));
+  run(case(
+    "c4 not taken",
+    state(0x0100, &[("f", 0x80)], ram.clone()),
+    state(0x0103, &[("f", 0x80)], ram),
+    3,
+  ));
+}
+
+#[test]
+fn conditional_ret_cycles() {
+  let ram = json!([[0x0100, 0xC8], [0xFFFC, 0x34], [0xFFFD, 0x12]]);
+  run(case(
+    "c8 taken",
+    state(0x0100, &[("sp", 0xFFFC), ("f", 0x80)], ram.clone()),
+    state(0x1234, &[("f", 0x80)], ram.clone()),
+    5,
+  ));
+  run(case(
+    "c8 not taken",
+    state(0x0100, &[("sp", 0xFFFC)], ram.clone()),
+    state(0x0101, &[("sp", 0xFFFC)], ram),
+    2,
+  ));
+
+  let ram = json!([[0x0100, 0xC9], [0xFFFC, 0x34], [0xFFFD, 0x12]]);
+  run(case(
+    "c9",
+    state(0x0100, &[("sp", 0xFFFC)], ram.clone()),
+    state(0x1234, &[], ram),
+    4,
+  ));
+}
+
+#[test]
+fn conditional_jp_cycles() {
+  let ram = json!([[0x0100, 0xDA], [0x0101, 0x34], [0x0102, 0x12]]);
+  run(case(
+    "da taken",
+    state(0x0100, &[("f", 0x10)], ram.clone()),
+    state(0x1234, &[("f", 0x10)], ram.clone()),
+    4,
+  ));
+  run(case(
+    "da not taken",
+    state(0x0100, &[], ram.clone()),
+    state(0x0103, &[], ram),
+    3,
+  ));
+}
+
+#[test]
+fn reti_enables_interrupts_immediately() {
+  let ram = json!([[0x0100, 0xD9], [0xFFFC, 0x34], [0xFFFD, 0x12]]);
+  let mut last = state(0x1234, &[], ram.clone());
+  last["ime"] = json!(1);
+  run(case("d9", state(0x0100, &[("sp", 0xFFFC)], ram), last, 4));
+}
@@ -0,0 +1,199 @@
+use crate::cartridge::patch::{apply, PatchFormat};
+use crate::cartridge::{Cartridge, CartridgeError, LoadOptions, PatchError, MAX_ROM_SIZE};
+use crate::tests::cartridge::rom_with_header;
+use crate::utils::crc32::crc32;
+
+// UPS/BPS variable length number.
+fn number(out: &mut Vec<u8>, mut n: usize) {
+  loop {
+    let x = (n & 0x7F) as u8;
+    n >>= 7;
+    if n == 0 {
+      out.push(0x80 | x);
+      return;
+    }
+    out.push(x);
+    n -= 1;
+  }
+}
+
+// Appends the source, target and patch CRC32 footer.
+fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+  patch.extend_from_slice(&crc32(source).to_le_bytes());
+  patch.extend_from_slice(&crc32(target).to_le_bytes());
+  let crc = crc32(&patch);
+  patch.extend_from_slice(&crc.to_le_bytes());
+  patch
+}
+
+#[test]
+fn crc32_check_value() {
+  assert_eq!(crc32(b"123456789"), 0xCBF43926);
+}
+
+#[test]
+fn detects_format_by_magic() {
+  assert_eq!(PatchFormat::detect(b"PATCHEOF"), Some(PatchFormat::Ips));
+  assert_eq!(PatchFormat::detect(b"UPS1"), Some(PatchFormat::Ups));
+  assert_eq!(PatchFormat::detect(b"BPS1"), Some(PatchFormat::Bps));
+  assert_eq!(apply(&[0; 4], b"NOPE"), Err(PatchError::UnknownFormat));
+}
+
+#[test]
+fn ips_records_runs_and_growth() {
+  let rom = vec![0u8; 8];
+  let mut patch = b"PATCH".to_vec();
+  // 2 bytes at 0x000001
+  patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+  // run of 4 x 0xCC at 0x000006, past the end of the ROM
+  patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0xCC]);
+  patch.extend_from_slice(b"EOF");
+
+  let out = apply(&rom, &patch).unwrap();
+  assert_eq!(out, [0, 0xAA, 0xBB, 0, 0, 0, 0xCC, 0xCC, 0xCC, 0xCC]);
+  assert_eq!(rom, [0; 8]);
+
+  // Truncation extension after EOF.
+  patch.extend_from_slice(&[0x00, 0x00, 0x03]);
+  assert_eq!(apply(&rom, &patch).unwrap(), [0, 0xAA, 0xBB]);
+
+  assert_eq!(apply(&rom, b"PATCH\x00\x00"), Err(PatchError::Truncated));
+}
+
+#[test]
+fn ups_xors_hunks_and_checks_crc() {
+  let source = vec![1, 2, 3, 4, 5, 6];
+  let target = vec![1, 2, 9, 4, 5, 6, 7];
+
+  let mut patch = b"UPS1".to_vec();
+  number(&mut patch, source.len());
+  number(&mut patch, target.len());
+  number(&mut patch, 2);
+  patch.extend_from_slice(&[3 ^ 9, 0x00]);
+  // The terminator covers index 3, so skip 4 and 5.
+  number(&mut patch, 2);
+  patch.extend_from_slice(&[7, 0x00]);
+  let patch = with_footer(patch, &source, &target);
+
+  assert_eq!(apply(&source, &patch).unwrap(), target);
+
+  let other = vec![0; 6];
+  assert!(matches!(apply(&other, &patch), Err(PatchError::SourceChecksum { .. })));
+
+  let mut corrupted = patch.clone();
+  corrupted[8] ^= 0xFF;
+  assert!(matches!(apply(&source, &corrupted), Err(PatchError::PatchChecksum { .. })));
+}
+
+#[test]
+fn bps_actions_and_checks_crc() {
+  let source = b"ABCDEFGH".to_vec();
+  let target = b"ABCDxyxyxyEF".to_vec();
+
+  let mut patch = b"BPS1".to_vec();
+  number(&mut patch, source.len());
+  number(&mut patch, target.len());
+  number(&mut patch, 0);
+  // SourceRead 4: "ABCD"
+  number(&mut patch, 3 << 2);
+  // TargetRead 2: "xy"
+  number(&mut patch, (1 << 2) | 1);
+  patch.extend_from_slice(b"xy");
+  // TargetCopy 4 from offset 4, overlapping its own output: "xyxy"
+  number(&mut patch, (3 << 2) | 3);
+  number(&mut patch, 4 << 1);
+  // SourceCopy 2 from offset 4: "EF"
+  number(&mut patch, (1 << 2) | 2);
+  number(&mut patch, 4 << 1);
+  let mut patch = with_footer(patch, &source, &target);
+
+  assert_eq!(apply(&source, &patch).unwrap(), target);
+
+  // Wrong target checksum, with a valid patch checksum.
+  let len = patch.len();
+  patch[len - 8] ^= 0xFF;
+  let crc = crc32(&patch[..len - 4]);
+  patch[len - 4..].copy_from_slice(&crc.to_le_bytes());
+  assert!(matches!(apply(&source, &patch), Err(PatchError::TargetChecksum { .. })));
+}
+
+#[test]
+fn rejects_targets_larger_than_any_rom() {
+  let source = vec![0; 8];
+
+  for magic in [b"UPS1", b"BPS1"] {
+    let mut patch = magic.to_vec();
+    number(&mut patch, source.len());
+    number(&mut patch, MAX_ROM_SIZE + 1);
+    number(&mut patch, 0);
+    let patch = with_footer(patch, &source, &[]);
+
+    assert_eq!(apply(&source, &patch), Err(PatchError::TargetTooLarge(MAX_ROM_SIZE + 1)));
+  }
+}
+
+#[test]
+fn rejects_numbers_that_overflow() {
+  let source = vec![0; 8];
+
+  // Eleven 7-bit groups need 77 bits.
+  let mut patch = b"UPS1".to_vec();
+  patch.extend_from_slice(&[0x7F; 10]);
+  patch.push(0xFF);
+  let patch = with_footer(patch, &source, &[]);
+
+  assert_eq!(apply(&source, &patch), Err(PatchError::Overflow));
+}
+
+#[test]
+fn bps_rejects_writes_past_the_target_size() {
+  let source = vec![0; 8];
+
+  // TargetCopy of 2^40 bytes into a 4 byte target.
+  let mut patch = b"BPS1".to_vec();
+  number(&mut patch, source.len());
+  number(&mut patch, 4);
+  number(&mut patch, 0);
+  number(&mut patch, (1 << 2) | 1);
+  patch.push(0xAA);
+  number(&mut patch, (1 << 42) | 3);
+  number(&mut patch, 1 << 1 | 1);
+  let patch = with_footer(patch, &source, &[]);
+
+  assert_eq!(apply(&source, &patch), Err(PatchError::OutOfBounds));
+}
+
+#[test]
+fn patches_are_applied_in_memory_when_loading() {
+  let dir = std::env::temp_dir().join(format!("gb-patch-test-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let rom_path = dir.join("game.gb");
+  let ips_path = dir.join("game.ips");
+
+  let rom = rom_with_header("ORIGINAL", 0x00, 0x00, 0x00);
+  std::fs::write(&rom_path, &rom).unwrap();
+
+  let mut patch = b"PATCH".to_vec();
+  patch.extend_from_slice(&[0x00, 0x01, 0x34, 0x00, 0x07]);
+  patch.extend_from_slice(b"PATCHED");
+  patch.extend_from_slice(b"EOF");
+  std::fs::write(&ips_path, &patch).unwrap();
+
+  let options = LoadOptions {
+    patches: crate::cartridge::find_patches(&rom_path),
+    ..LoadOptions::default()
+  };
+  assert_eq!(options.patches, std::slice::from_ref(&ips_path));
+
+  let cartridge = Cartridge::from_file_with_options(&rom_path, &options).unwrap();
+  assert_eq!(cartridge.header.title, "PATCHEDL");
+  assert_eq!(std::fs::read(&rom_path).unwrap(), rom);
+
+  std::fs::write(&ips_path, b"PATCH").unwrap();
+  assert!(matches!(
+    Cartridge::from_file_with_options(&rom_path, &options),
+    Err(CartridgeError::Patch(_, PatchError::Truncated))
+  ));
+
+  std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,587 @@
+use crate::bus::Bus;
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::memory::MemoryInterface;
+use crate::ppu::{
+  Mode, Ppu, Sprite, BGP_ADDR, DOTS_PER_LINE, DRAWING_DOTS, LCDC_ADDR, LYC_ADDR, LY_ADDR,
+  OAM_SCAN_DOTS, OBP0_ADDR, OBP1_ADDR, SCREEN_HEIGHT, SCREEN_WIDTH, SCX_ADDR, SCY_ADDR, STAT_ADDR,
+  WX_ADDR, WY_ADDR,
+};
+
+// Identity palette, so shades equal color indices.
+const BGP: u8 = 0b11_10_01_00;
+
+// LCD on, 0x8000 tile data, BG on.
+const LCDC: u8 = 0x91;
+
+// Fills tile `index` in the 0x8000 block with a single color.
+fn solid_tile(ppu: &mut Ppu, index: usize, color: u8) {
+  let low = if color & 1 != 0 { 0xFF } else { 0x00 };
+  let high = if color & 2 != 0 { 0xFF } else { 0x00 };
+  for row in 0..8 {
+    ppu.vram[index * 16 + row * 2] = low;
+    ppu.vram[index * 16 + row * 2 + 1] = high;
+  }
+}
+
+// LCD on, 0x8000 tile data, BG and objects on.
+const LCDC_OBJ: u8 = LCDC | 0x02;
+
+fn setup(lcdc: u8) -> Ppu {
+  let mut ppu = Ppu::new();
+  ppu.write(BGP_ADDR, BGP);
+  ppu.write(OBP0_ADDR, BGP);
+  ppu.write(OBP1_ADDR, BGP);
+  ppu.write(LCDC_ADDR, lcdc);
+  ppu
+}
+
+// Puts object `index` at screen position (`x`, `y`).
+fn sprite(ppu: &mut Ppu, index: usize, x: i16, y: i16, tile: u8, flags: u8) {
+  let entry = [(y + 16) as u8, (x + 8) as u8, tile, flags];
+  ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&entry);
+}
+
+// Runs `dots` dots and returns the interrupts requested.
+fn tick(ppu: &mut Ppu, dots: usize) -> u8 {
+  let mut interrupts = InterruptController::new();
+  ppu.tick(dots, &mut interrupts);
+  interrupts.flag
+}
+
+fn render_frame(ppu: &mut Ppu) {
+  tick(ppu, DOTS_PER_LINE * SCREEN_HEIGHT);
+}
+
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+  ppu.framebuffer()[y * SCREEN_WIDTH + x]
+}
+
+#[test]
+fn ly_advances_every_line_and_wraps() {
+  let mut ppu = setup(LCDC);
+  tick(&mut ppu, DOTS_PER_LINE - 4);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+  tick(&mut ppu, 4);
+  assert_eq!(ppu.read(LY_ADDR), 1);
+  tick(&mut ppu, DOTS_PER_LINE * 153);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+
+  // Read only.
+  ppu.write(LY_ADDR, 0x42);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+}
+
+#[test]
+fn lcd_off_resets_ly_and_stops() {
+  let mut ppu = setup(LCDC);
+  tick(&mut ppu, DOTS_PER_LINE * 10);
+  ppu.write(LCDC_ADDR, LCDC & 0x7F);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+  tick(&mut ppu, DOTS_PER_LINE * 10);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+}
+
+#[test]
+fn renders_background_with_palette() {
+  let mut ppu = setup(LCDC);
+  solid_tile(&mut ppu, 1, 3);
+  // Tile (1, 1) of the 0x9800 map.
+  ppu.vram[0x1800 + 32 + 1] = 1;
+  ppu.write(BGP_ADDR, 0b01_00_00_00);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 7, 8), 0);
+  assert_eq!(pixel(&ppu, 8, 8), 1);
+  assert_eq!(pixel(&ppu, 15, 15), 1);
+  assert_eq!(pixel(&ppu, 16, 15), 0);
+}
+
+#[test]
+fn decodes_tile_rows_msb_first() {
+  let mut ppu = setup(LCDC);
+  // Colors 0, 1, 2, 3 in the first four pixels of row 0.
+  ppu.vram[0] = 0b0101_0000;
+  ppu.vram[1] = 0b0011_0000;
+
+  render_frame(&mut ppu);
+
+  let row: Vec<u8> = (0..5).map(|x| pixel(&ppu, x, 0)).collect();
+  assert_eq!(row, [0, 1, 2, 3, 0]);
+}
+
+#[test]
+fn signed_tile_data_addressing() {
+  // 0x8800 addressing: tile 0 is at 0x9000 and tile 0xFF at 0x8FF0.
+  let mut ppu = setup(LCDC & !0x10);
+  solid_tile(&mut ppu, 0x100, 1);
+  solid_tile(&mut ppu, 0xFF, 2);
+  ppu.vram[0x1800 + 1] = 0xFF;
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 0, 0), 1);
+  assert_eq!(pixel(&ppu, 8, 0), 2);
+}
+
+#[test]
+fn scrolls_and_wraps_background() {
+  let mut ppu = setup(LCDC);
+  solid_tile(&mut ppu, 1, 3);
+  // Last tile of the map, at (248, 248).
+  ppu.vram[0x1800 + 31 * 32 + 31] = 1;
+  ppu.write(SCX_ADDR, 252);
+  ppu.write(SCY_ADDR, 250);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 0, 0), 3);
+  assert_eq!(pixel(&ppu, 3, 5), 3);
+  assert_eq!(pixel(&ppu, 4, 0), 0);
+  assert_eq!(pixel(&ppu, 0, 6), 0);
+}
+
+#[test]
+fn selects_background_map() {
+  let mut ppu = setup(LCDC | 0x08);
+  solid_tile(&mut ppu, 1, 2);
+  ppu.vram[0x1C00] = 1;
+
+  render_frame(&mut ppu);
+  assert_eq!(pixel(&ppu, 0, 0), 2);
+}
+
+#[test]
+fn renders_window_over_background() {
+  // Window on, using the 0x9C00 map.
+  let mut ppu = setup(LCDC | 0x20 | 0x40);
+  solid_tile(&mut ppu, 1, 3);
+  for tile in 0..0x400 {
+    ppu.vram[0x1C00 + tile] = 1;
+  }
+  ppu.write(WX_ADDR, 7 + 80);
+  ppu.write(WY_ADDR, 100);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 80, 99), 0);
+  assert_eq!(pixel(&ppu, 79, 100), 0);
+  assert_eq!(pixel(&ppu, 80, 100), 3);
+  assert_eq!(pixel(&ppu, 159, 143), 3);
+}
+
+#[test]
+fn window_line_counter_skips_hidden_lines() {
+  let mut ppu = setup(LCDC | 0x20 | 0x40);
+  // Window row 0 is color 1, row 1 color 2.
+  solid_tile(&mut ppu, 1, 1);
+  ppu.vram[0x1C00] = 1;
+  solid_tile(&mut ppu, 2, 2);
+  ppu.vram[0x1C00 + 32] = 2;
+  ppu.write(WX_ADDR, 7);
+
+  // Draw 4 lines of window, hide it for 10 lines and show it again.
+  tick(&mut ppu, DOTS_PER_LINE * 4);
+  ppu.write(WX_ADDR, 200);
+  tick(&mut ppu, DOTS_PER_LINE * 10);
+  ppu.write(WX_ADDR, 7);
+  tick(&mut ppu, DOTS_PER_LINE * 8);
+
+  assert_eq!(pixel(&ppu, 0, 3), 1);
+  assert_eq!(pixel(&ppu, 0, 14), 1);
+  assert_eq!(pixel(&ppu, 0, 17), 1);
+  assert_eq!(pixel(&ppu, 0, 18), 2);
+}
+
+#[test]
+fn bg_disabled_is_blank() {
+  let mut ppu = setup(LCDC & !0x01);
+  solid_tile(&mut ppu, 0, 3);
+  ppu.write(BGP_ADDR, 0b11_11_11_10);
+
+  render_frame(&mut ppu);
+  assert!(ppu.framebuffer().iter().all(|&shade| shade == 2));
+}
+
+#[test]
+fn draws_objects_with_their_palette() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 1);
+  sprite(&mut ppu, 0, 10, 20, 1, 0x00);
+  sprite(&mut ppu, 1, 30, 20, 1, 0x10);
+  ppu.write(OBP0_ADDR, 0b00_00_10_00);
+  ppu.write(OBP1_ADDR, 0b00_00_11_00);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 9, 20), 0);
+  assert_eq!(pixel(&ppu, 10, 20), 2);
+  assert_eq!(pixel(&ppu, 17, 27), 2);
+  assert_eq!(pixel(&ppu, 18, 27), 0);
+  assert_eq!(pixel(&ppu, 10, 28), 0);
+  assert_eq!(pixel(&ppu, 30, 20), 3);
+}
+
+#[test]
+fn objects_disabled_by_lcdc() {
+  let mut ppu = setup(LCDC);
+  solid_tile(&mut ppu, 1, 3);
+  sprite(&mut ppu, 0, 0, 0, 1, 0x00);
+
+  render_frame(&mut ppu);
+  assert_eq!(pixel(&ppu, 0, 0), 0);
+}
+
+#[test]
+fn objects_are_clipped_at_screen_edges() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 3);
+  sprite(&mut ppu, 0, -4, -4, 1, 0x00);
+  sprite(&mut ppu, 1, 156, 140, 1, 0x00);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 3, 3), 3);
+  assert_eq!(pixel(&ppu, 4, 3), 0);
+  assert_eq!(pixel(&ppu, 156, 140), 3);
+  assert_eq!(pixel(&ppu, 159, 143), 3);
+}
+
+#[test]
+fn color_0_is_transparent() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 2);
+  ppu.vram[0x1800] = 1;
+  // Left half color 0, right half color 3.
+  for row in 0..8 {
+    ppu.vram[2 * 16 + row * 2] = 0x0F;
+    ppu.vram[2 * 16 + row * 2 + 1] = 0x0F;
+  }
+  sprite(&mut ppu, 0, 0, 0, 2, 0x00);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 3, 0), 2);
+  assert_eq!(pixel(&ppu, 4, 0), 3);
+}
+
+#[test]
+fn flips_objects() {
+  let mut ppu = setup(LCDC_OBJ);
+  // Only the top left pixel is set.
+  ppu.vram[16] = 0x80;
+  ppu.vram[17] = 0x80;
+  sprite(&mut ppu, 0, 0, 0, 1, 0x00);
+  sprite(&mut ppu, 1, 8, 0, 1, 0x20);
+  sprite(&mut ppu, 2, 16, 0, 1, 0x40);
+  sprite(&mut ppu, 3, 24, 0, 1, 0x60);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 0, 0), 3);
+  assert_eq!(pixel(&ppu, 15, 0), 3);
+  assert_eq!(pixel(&ppu, 16, 7), 3);
+  assert_eq!(pixel(&ppu, 31, 7), 3);
+  assert_eq!(pixel(&ppu, 8, 0), 0);
+  assert_eq!(pixel(&ppu, 16, 0), 0);
+}
+
+#[test]
+fn tall_objects_use_tile_pairs() {
+  let mut ppu = setup(LCDC_OBJ | 0x04);
+  solid_tile(&mut ppu, 2, 1);
+  solid_tile(&mut ppu, 3, 2);
+  // Bit 0 of the tile number is ignored.
+  sprite(&mut ppu, 0, 0, 0, 3, 0x00);
+  sprite(&mut ppu, 1, 8, 0, 2, 0x40);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 0, 0), 1);
+  assert_eq!(pixel(&ppu, 0, 15), 2);
+  assert_eq!(pixel(&ppu, 0, 16), 0);
+  assert_eq!(pixel(&ppu, 8, 0), 2);
+  assert_eq!(pixel(&ppu, 8, 15), 1);
+}
+
+#[test]
+fn bg_priority_hides_objects_behind_colors_1_to_3() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 1);
+  solid_tile(&mut ppu, 2, 3);
+  ppu.vram[0x1800] = 1;
+  sprite(&mut ppu, 0, 4, 0, 2, 0x80);
+
+  render_frame(&mut ppu);
+
+  // Over BG color 1 at x 4-7, over color 0 at x 8-11.
+  assert_eq!(pixel(&ppu, 4, 0), 1);
+  assert_eq!(pixel(&ppu, 8, 0), 3);
+}
+
+#[test]
+fn hidden_object_still_wins_priority() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 1);
+  solid_tile(&mut ppu, 2, 3);
+  solid_tile(&mut ppu, 3, 2);
+  ppu.vram[0x1800] = 1;
+  // The object with the smaller X is behind the BG, the other one doesn't
+  // show through it.
+  sprite(&mut ppu, 0, 2, 0, 3, 0x00);
+  sprite(&mut ppu, 1, 0, 0, 2, 0x80);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 1, 0), 1);
+  assert_eq!(pixel(&ppu, 7, 0), 1);
+  assert_eq!(pixel(&ppu, 8, 0), 2);
+}
+
+#[test]
+fn smaller_x_then_oam_order_wins() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 1);
+  solid_tile(&mut ppu, 2, 2);
+  solid_tile(&mut ppu, 3, 3);
+  sprite(&mut ppu, 0, 4, 0, 1, 0x00);
+  sprite(&mut ppu, 1, 0, 0, 2, 0x00);
+  sprite(&mut ppu, 2, 20, 0, 1, 0x00);
+  sprite(&mut ppu, 3, 20, 0, 3, 0x00);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(pixel(&ppu, 5, 0), 2);
+  assert_eq!(pixel(&ppu, 9, 0), 1);
+  assert_eq!(pixel(&ppu, 20, 0), 1);
+}
+
+#[test]
+fn ten_objects_per_line() {
+  let mut ppu = setup(LCDC_OBJ);
+  solid_tile(&mut ppu, 1, 3);
+  // 10 objects off screen on the left still take up the line.
+  for i in 0..10 {
+    sprite(&mut ppu, i, -8, 50, 1, 0x00);
+  }
+  sprite(&mut ppu, 10, 0, 50, 1, 0x00);
+  sprite(&mut ppu, 11, 8, 52, 1, 0x00);
+
+  render_frame(&mut ppu);
+
+  assert_eq!(ppu.oam_scan(50).len(), 10);
+  assert_eq!(pixel(&ppu, 0, 50), 0);
+  assert_eq!(pixel(&ppu, 8, 51), 0);
+  assert_eq!(pixel(&ppu, 0, 58), 0);
+  // Object 11 shows up once the off screen objects end.
+  assert_eq!(pixel(&ppu, 0, 57), 0);
+  assert_eq!(pixel(&ppu, 8, 58), 3);
+}
+
+#[test]
+fn oam_scan_sorts_by_x_keeping_oam_order() {
+  let mut ppu = setup(LCDC_OBJ);
+  sprite(&mut ppu, 0, 30, 0, 0, 0x00);
+  sprite(&mut ppu, 1, 10, 0, 1, 0x00);
+  sprite(&mut ppu, 2, 30, 0, 2, 0x00);
+  sprite(&mut ppu, 3, 10, 9, 3, 0x00);
+
+  let tiles: Vec<u8> = ppu.oam_scan(0).iter().map(|s: &Sprite| s.tile).collect();
+  assert_eq!(tiles, [1, 0, 2]);
+}
+
+#[test]
+fn cycles_through_modes_on_each_line() {
+  let mut ppu = setup(LCDC);
+  // The first line after turning the LCD on has no mode 2.
+  assert_eq!(ppu.mode, Mode::HBlank);
+  tick(&mut ppu, DOTS_PER_LINE);
+
+  assert_eq!(ppu.read(LY_ADDR), 1);
+  assert_eq!(ppu.mode, Mode::OamScan);
+  tick(&mut ppu, OAM_SCAN_DOTS - 1);
+  assert_eq!(ppu.mode, Mode::OamScan);
+  tick(&mut ppu, 1);
+  assert_eq!(ppu.mode, Mode::Drawing);
+  tick(&mut ppu, DRAWING_DOTS - 1);
+  assert_eq!(ppu.mode, Mode::Drawing);
+  tick(&mut ppu, 1);
+  assert_eq!(ppu.mode, Mode::HBlank);
+  assert_eq!(ppu.read(STAT_ADDR) & 0b11, 0);
+
+  tick(&mut ppu, DOTS_PER_LINE * 143 - OAM_SCAN_DOTS - DRAWING_DOTS);
+  assert_eq!(ppu.read(LY_ADDR), 144);
+  assert_eq!(ppu.mode, Mode::VBlank);
+  assert_eq!(ppu.read(STAT_ADDR) & 0b11, 1);
+
+  tick(&mut ppu, DOTS_PER_LINE * 10);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+  assert_eq!(ppu.mode, Mode::OamScan);
+}
+
+#[test]
+fn fine_scroll_lengthens_mode_3() {
+  let mut ppu = setup(LCDC);
+  ppu.write(SCX_ADDR, 5);
+  tick(&mut ppu, OAM_SCAN_DOTS + DRAWING_DOTS + 4);
+  assert_eq!(ppu.mode, Mode::Drawing);
+  tick(&mut ppu, 1);
+  assert_eq!(ppu.mode, Mode::HBlank);
+}
+
+#[test]
+fn requests_vblank_once_per_frame() {
+  let mut ppu = setup(LCDC);
+  let flags = tick(&mut ppu, DOTS_PER_LINE * SCREEN_HEIGHT - 1);
+  assert_eq!(flags & Interrupt::VBlank.bit(), 0);
+
+  let mut interrupts = InterruptController::new();
+  ppu.tick(1, &mut interrupts);
+  assert_eq!(interrupts.flag, Interrupt::VBlank.bit());
+
+  interrupts.flag = 0;
+  ppu.tick(DOTS_PER_LINE * 10 - 1, &mut interrupts);
+  assert_eq!(interrupts.flag, 0);
+}
+
+#[test]
+fn compares_ly_with_lyc() {
+  let mut ppu = setup(LCDC);
+  ppu.write(LYC_ADDR, 2);
+  assert_eq!(ppu.read(STAT_ADDR) & 0x04, 0);
+
+  tick(&mut ppu, DOTS_PER_LINE * 2);
+  assert_eq!(ppu.read(STAT_ADDR) & 0x04, 0x04);
+  tick(&mut ppu, DOTS_PER_LINE);
+  assert_eq!(ppu.read(STAT_ADDR) & 0x04, 0);
+}
+
+#[test]
+fn stat_register_bits() {
+  let mut ppu = setup(LCDC);
+  ppu.write(STAT_ADDR, 0xFF);
+  // Bit 7 reads 1, the coincidence flag and mode can't be written.
+  assert_eq!(ppu.read(STAT_ADDR), 0xFC);
+  ppu.write(STAT_ADDR, 0x00);
+  assert_eq!(ppu.read(STAT_ADDR), 0x84);
+}
+
+#[test]
+fn lyc_interrupt() {
+  let mut ppu = setup(LCDC);
+  ppu.write(STAT_ADDR, 0x40);
+  ppu.write(LYC_ADDR, 10);
+
+  assert_eq!(tick(&mut ppu, DOTS_PER_LINE * 10 - 1), 0);
+  assert_eq!(tick(&mut ppu, 1), Interrupt::LcdStat.bit());
+  assert_eq!(tick(&mut ppu, DOTS_PER_LINE), 0);
+}
+
+#[test]
+fn mode_interrupts() {
+  let mut ppu = setup(LCDC);
+  tick(&mut ppu, DOTS_PER_LINE);
+
+  // Mode 2 starts every visible line.
+  ppu.write(STAT_ADDR, 0x20);
+  assert_eq!(tick(&mut ppu, DOTS_PER_LINE), Interrupt::LcdStat.bit());
+
+  // Mode 0 after mode 3.
+  ppu.write(STAT_ADDR, 0x08);
+  assert_eq!(tick(&mut ppu, OAM_SCAN_DOTS + DRAWING_DOTS - 1), 0);
+  assert_eq!(tick(&mut ppu, 1), Interrupt::LcdStat.bit());
+
+  // Mode 1 at line 144.
+  ppu.write(STAT_ADDR, 0x10);
+  let flags = tick(&mut ppu, DOTS_PER_LINE * 143);
+  assert_eq!(flags, Interrupt::VBlank.bit() | Interrupt::LcdStat.bit());
+}
+
+#[test]
+fn stat_blocking() {
+  let mut ppu = setup(LCDC);
+  tick(&mut ppu, DOTS_PER_LINE * 4);
+
+  // LY == LYC holds the line high for all of line 5, so mode 0 doesn't
+  // fire. Mode 0 of line 6 follows mode 3 and does.
+  ppu.write(STAT_ADDR, 0x48);
+  ppu.write(LYC_ADDR, 5);
+  let mut interrupts = InterruptController::new();
+  ppu.tick(DOTS_PER_LINE, &mut interrupts);
+  assert_eq!(interrupts.flag, Interrupt::LcdStat.bit());
+
+  interrupts.flag = 0;
+  ppu.tick(DOTS_PER_LINE, &mut interrupts);
+  assert_eq!(interrupts.flag, 0);
+  ppu.tick(OAM_SCAN_DOTS + DRAWING_DOTS, &mut interrupts);
+  assert_eq!(interrupts.flag, Interrupt::LcdStat.bit());
+
+  // Mode 0 into mode 2 keeps the line high as well.
+  interrupts.flag = 0;
+  ppu.write(STAT_ADDR, 0x28);
+  ppu.tick(DOTS_PER_LINE - OAM_SCAN_DOTS - DRAWING_DOTS, &mut interrupts);
+  assert_eq!(interrupts.flag, 0);
+}
+
+#[test]
+fn lcd_off_stops_in_mode_0() {
+  let mut ppu = setup(LCDC);
+  ppu.write(STAT_ADDR, 0x78);
+  tick(&mut ppu, DOTS_PER_LINE * 150);
+  assert_eq!(ppu.mode, Mode::VBlank);
+
+  ppu.write(LCDC_ADDR, LCDC & 0x7F);
+  assert_eq!(ppu.mode, Mode::HBlank);
+  assert_eq!(ppu.read(STAT_ADDR) & 0b11, 0);
+  assert_eq!(tick(&mut ppu, DOTS_PER_LINE * 200), 0);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+
+  // Back on, line 0 starts over.
+  ppu.write(LCDC_ADDR, LCDC);
+  tick(&mut ppu, OAM_SCAN_DOTS);
+  assert_eq!(ppu.mode, Mode::Drawing);
+  assert_eq!(ppu.read(LY_ADDR), 0);
+}
+
+#[test]
+fn blocks_vram_and_oam_by_mode() {
+  let mut bus = Bus::new();
+  bus.poke(0x8000, 0x12);
+  bus.poke(0xFE00, 0x34);
+  bus.write(LCDC_ADDR, LCDC);
+  bus.tick(DOTS_PER_LINE);
+
+  // Mode 2: OAM blocked.
+  assert_eq!(bus.ppu.mode, Mode::OamScan);
+  assert_eq!(bus.read(0x8000), 0x12);
+  assert_eq!(bus.read(0xFE00), 0xFF);
+  bus.write(0xFE00, 0x56);
+  assert_eq!(bus.ppu.oam[0], 0x34);
+
+  // Mode 3: both blocked.
+  bus.tick(OAM_SCAN_DOTS);
+  assert_eq!(bus.ppu.mode, Mode::Drawing);
+  assert_eq!(bus.read(0x8000), 0xFF);
+  assert_eq!(bus.read(0xFE00), 0xFF);
+  bus.write(0x8000, 0x56);
+  assert_eq!(bus.ppu.vram[0], 0x12);
+
+  // Mode 0: both accessible.
+  bus.tick(DRAWING_DOTS);
+  assert_eq!(bus.ppu.mode, Mode::HBlank);
+  bus.write(0x8000, 0x56);
+  bus.write(0xFE00, 0x78);
+  assert_eq!(bus.read(0x8000), 0x56);
+  assert_eq!(bus.read(0xFE00), 0x78);
+}
+
+#[test]
+fn bus_routes_lcd_registers() {
+  let mut bus = Bus::new();
+  bus.write(LCDC_ADDR, LCDC);
+  bus.write(LYC_ADDR, 3);
+  bus.tick(DOTS_PER_LINE * 3);
+
+  assert_eq!(bus.read(LY_ADDR), 3);
+  assert_eq!(bus.read(STAT_ADDR) & 0x04, 0x04);
+}
@@ -0,0 +1,135 @@
+// Regression vectors in the SM83 single-step JSON format for opcodes whose
+// flag or cycle handling was previously wrong. `state`, `case` and `run`
+// are shared with the other hand written vectors.
+use serde_json::{json, Value};
+
+use super::TestSuite;
+use crate::memory::FlatRam;
+
+pub(super) fn state(pc: u16, registers: &[(&str, u64)], ram: Value) -> Value {
+  let mut state = json!({
+    "pc": pc, "sp": 0xFFFE,
+    "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0,
+    "ram": ram,
+  });
+
+  for &(name, value) in registers {
+    state[name] = json!(value);
+  }
+
+  state
+}
+
+pub(super) fn case(name: &str, initial: Value, last: Value, m_cycles: usize) -> Value {
+  json!({
+    "name": name,
+    "initial": initial,
+    "final": last,
+    "cycles": vec![json!(null); m_cycles],
+  })
+}
+
+pub(super) fn run(case: Value) {
+  let mut test = TestSuite::<FlatRam>::new();
+  if let Err(e) = test.run_case(&case) {
+    panic!("{}", e);
+  }
+}
+
+#[test]
+fn cp_n_sets_carry_from_borrow() {
+  let ram = json!([[0x0100, 0xFE], [0x0101, 0x10]]);
+  run(case(
+    "fe no borrow",
+    state(0x0100, &[("a", 0x90)], ram.clone()),
+    state(0x0102, &[("a", 0x90), ("f", 0x40)], ram),
+    2,
+  ));
+
+  let ram = json!([[0x0100, 0xFE], [0x0101, 0x20]]);
+  run(case(
+    "fe borrow",
+    state(0x0100, &[("a", 0x10)], ram.clone()),
+    state(0x0102, &[("a", 0x10), ("f", 0x50)], ram),
+    2,
+  ));
+}
+
+#[test]
+fn cp_n_sets_half_carry_from_nibbles() {
+  let ram = json!([[0x0100, 0xFE], [0x0101, 0x0F]]);
+  run(case(
+    "fe half borrow",
+    state(0x0100, &[("a", 0x3C)], ram.clone()),
+    state(0x0102, &[("a", 0x3C), ("f", 0x60)], ram),
+    2,
+  ));
+
+  let ram = json!([[0x0100, 0xFE], [0x0101, 0x42]]);
+  run(case(
+    "fe equal",
+    state(0x0100, &[("a", 0x42)], ram.clone()),
+    state(0x0102, &[("a", 0x42), ("f", 0xC0)], ram),
+    2,
+  ));
+}
+
+#[test]
+fn jr_nz_jumps_when_zero_is_clear() {
+  let ram = json!([[0x0200, 0x20], [0x0201, 0x05]]);
+  run(case("20 taken", state(0x0200, &[], ram.clone()), state(0x0207, &[], ram), 3));
+
+  let ram = json!([[0x0200, 0x20], [0x0201, 0xFE]]);
+  run(case(
+    "20 taken backwards",
+    state(0x0200, &[], ram.clone()),
+    state(0x0200, &[], ram),
+    3,
+  ));
+
+  let ram = json!([[0x0200, 0x20], [0x0201, 0x05]]);
+  run(case(
+    "20 not taken",
+    state(0x0200, &[("f", 0x80)], ram.clone()),
+    state(0x0202, &[("f", 0x80)], ram),
+    2,
+  ));
+}
+
+#[test]
+fn bit_7_h_sets_zero_when_bit_is_clear() {
+  let ram = json!([[0x0100, 0xCB], [0x0101, 0x7C]]);
+  run(case(
+    "cb 7c set",
+    state(0x0100, &[("h", 0x80), ("f", 0x10)], ram.clone()),
+    state(0x0102, &[("h", 0x80), ("f", 0x30)], ram.clone()),
+    2,
+  ));
+
+  run(case(
+    "cb 7c clear",
+    state(0x0100, &[("h", 0x7F)], ram.clone()),
+    state(0x0102, &[("h", 0x7F), ("f", 0xA0)], ram),
+    2,
+  ));
+}
+
+#[test]
+fn cb_hl_operands_take_extra_cycles() {
+  let initial = json!([[0x0100, 0xCB], [0x0101, 0x46], [0xC000, 0x01]]);
+  run(case(
+    "cb 46",
+    state(0x0100, &[("h", 0xC0)], initial.clone()),
+    state(0x0102, &[("h", 0xC0), ("f", 0x20)], initial),
+    3,
+  ));
+
+  let initial = json!([[0x0100, 0xCB], [0x0101, 0x86], [0xC000, 0xFF]]);
+  let last = json!([[0x0100, 0xCB], [0x0101, 0x86], [0xC000, 0xFE]]);
+  run(case(
+    "cb 86",
+    state(0x0100, &[("h", 0xC0)], initial),
+    state(0x0102, &[("h", 0xC0)], last),
+    4,
+  ));
+}
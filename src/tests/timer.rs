@@ -0,0 +1,191 @@
+use super::cpu_with_program;
+use crate::cpu::Cpu;
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::timer::{Timer, DIV_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR};
+
+// TAC enable, 262144 Hz: TIMA increments every 16 T-cycles.
+const TAC_16: u8 = 0b101;
+
+#[test]
+fn div_is_upper_byte_of_divider() {
+  let mut timer = Timer::new();
+  let mut interrupts = InterruptController::new();
+
+  timer.tick(252, &mut interrupts);
+  assert_eq!(timer.read(DIV_ADDR), 0x00);
+  timer.tick(4, &mut interrupts);
+  assert_eq!(timer.read(DIV_ADDR), 0x01);
+
+  timer.write(DIV_ADDR, 0x7F);
+  assert_eq!(timer.divider, 0);
+}
+
+#[test]
+fn tac_selects_tima_frequency() {
+  let mut interrupts = InterruptController::new();
+
+  for (tac, period) in [(0b100, 1024), (0b101, 16), (0b110, 64), (0b111, 256)] {
+    let mut timer = Timer::new();
+    timer.write(TAC_ADDR, tac);
+    timer.tick(period - 4, &mut interrupts);
+    assert_eq!(timer.read(TIMA_ADDR), 0, "TAC {:03b}", tac);
+    timer.tick(4, &mut interrupts);
+    assert_eq!(timer.read(TIMA_ADDR), 1, "TAC {:03b}", tac);
+  }
+
+  // Disabled.
+  let mut timer = Timer::new();
+  timer.write(TAC_ADDR, 0b001);
+  timer.tick(1024, &mut interrupts);
+  assert_eq!(timer.read(TIMA_ADDR), 0);
+  assert_eq!(timer.read(TAC_ADDR), 0xF9);
+}
+
+#[test]
+fn tima_overflow_reloads_tma_one_m_cycle_later() {
+  let mut timer = Timer::new();
+  let mut interrupts = InterruptController::new();
+  timer.write(TAC_ADDR, TAC_16);
+  timer.write(TMA_ADDR, 0x42);
+  timer.write(TIMA_ADDR, 0xFF);
+
+  timer.tick(16, &mut interrupts);
+  assert_eq!(timer.read(TIMA_ADDR), 0x00);
+  assert_eq!(interrupts.flag, 0);
+
+  timer.tick(4, &mut interrupts);
+  assert_eq!(timer.read(TIMA_ADDR), 0x42);
+  assert_eq!(interrupts.flag, Interrupt::Timer.bit());
+
+  // TIMA writes are ignored during the reload cycle, TMA writes go through.
+  timer.write(TIMA_ADDR, 0x10);
+  assert_eq!(timer.read(TIMA_ADDR), 0x42);
+  timer.write(TMA_ADDR, 0x55);
+  assert_eq!(timer.read(TIMA_ADDR), 0x55);
+}
+
+#[test]
+fn writing_tima_during_overflow_cancels_reload() {
+  let mut timer = Timer::new();
+  let mut interrupts = InterruptController::new();
+  timer.write(TAC_ADDR, TAC_16);
+  timer.write(TMA_ADDR, 0x42);
+  timer.write(TIMA_ADDR, 0xFF);
+
+  timer.tick(16, &mut interrupts);
+  timer.write(TIMA_ADDR, 0x10);
+  timer.tick(4, &mut interrupts);
+
+  assert_eq!(timer.read(TIMA_ADDR), 0x10);
+  assert_eq!(interrupts.flag, 0);
+}
+
+#[test]
+fn div_reset_falling_edge_increments_tima() {
+  let mut timer = Timer::new();
+  let mut interrupts = InterruptController::new();
+  timer.write(TAC_ADDR, TAC_16);
+
+  // Divider bit 3 is set.
+  timer.tick(8, &mut interrupts);
+  timer.write(DIV_ADDR, 0);
+  assert_eq!(timer.read(TIMA_ADDR), 1);
+
+  // Bit 3 is clear, no edge.
+  timer.tick(4, &mut interrupts);
+  timer.write(DIV_ADDR, 0);
+  assert_eq!(timer.read(TIMA_ADDR), 1);
+}
+
+#[test]
+fn tac_write_falling_edge_increments_tima() {
+  let mut timer = Timer::new();
+  let mut interrupts = InterruptController::new();
+  timer.write(TAC_ADDR, TAC_16);
+  timer.tick(8, &mut interrupts);
+
+  // Disabling the timer while the selected bit is set.
+  timer.write(TAC_ADDR, 0b001);
+  assert_eq!(timer.read(TIMA_ADDR), 1);
+
+  // Switching to a bit that is clear (bit 9).
+  timer.write(TAC_ADDR, TAC_16);
+  timer.write(TAC_ADDR, 0b100);
+  assert_eq!(timer.read(TIMA_ADDR), 2);
+}
+
+#[test]
+fn cpu_steps_drive_the_timer() {
+  // LD A,0xF0; LDH (0x05),A; LD A,0x05; LDH (0x07),A; HALT
+  let mut cpu = cpu_with_program(&[0x3E, 0xF0, 0xE0, 0x05, 0x3E, 0x05, 0xE0, 0x07, 0x76]);
+  cpu.bus.interrupts.enable = Interrupt::Timer.bit();
+
+  for _ in 0..5 {
+    cpu.step().unwrap();
+  }
+  assert!(cpu.halted);
+
+  // The remaining increments of 16 T-cycles each, plus the reload.
+  let mut cycles = 0;
+  while cpu.halted {
+    cpu.step().unwrap();
+    cycles += cpu.cycles;
+    assert!(cycles < 1024);
+  }
+  assert!((14 * 16..=16 * 16 + 4).contains(&cycles), "{}", cycles);
+  assert_eq!(cpu.bus.timer.tima, 0x00);
+  assert_eq!(cpu.bus.interrupts.flag, Interrupt::Timer.bit());
+}
+
+// CPU running `program` with TIMA about to overflow on the M-cycle that
+// takes the divider from 0x0C to 0x10, a falling edge of bit 3.
+fn cpu_before_overflow(program: &[u8], divider: u16) -> Cpu {
+  let mut cpu = cpu_with_program(program);
+  cpu.bus.timer.tac = TAC_16;
+  cpu.bus.timer.tma = 0x42;
+  cpu.bus.timer.tima = 0xFF;
+  cpu.bus.timer.divider = divider;
+  cpu
+}
+
+#[test]
+fn reads_see_the_timer_on_their_own_m_cycle() {
+  // LDH A, (0x05) reads TIMA on its third M-cycle.
+  let program = [0xF0, 0x05, 0x00];
+
+  // Overflow on the third M-cycle: TIMA reads 0x00 before the reload.
+  let mut cpu = cpu_before_overflow(&program, 0x0C - 8);
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.a, 0x00);
+  assert_eq!(cpu.bus.interrupts.flag, 0);
+  cpu.step().unwrap();
+  assert_eq!(cpu.bus.timer.tima, 0x42);
+  assert_eq!(cpu.bus.interrupts.flag, Interrupt::Timer.bit());
+
+  // Overflow on the second M-cycle: the read already sees TMA.
+  let mut cpu = cpu_before_overflow(&program, 0x0C - 4);
+  cpu.step().unwrap();
+  assert_eq!(cpu.reg.a, 0x42);
+  assert_eq!(cpu.bus.interrupts.flag, Interrupt::Timer.bit());
+}
+
+#[test]
+fn writes_hit_the_timer_on_their_own_m_cycle() {
+  // LDH (0x05), A writes TIMA on its third M-cycle.
+  let program = [0xE0, 0x05, 0x00];
+
+  // Writing during the overflow M-cycle cancels the reload.
+  let mut cpu = cpu_before_overflow(&program, 0x0C - 8);
+  cpu.reg.a = 0x10;
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+  assert_eq!(cpu.bus.timer.tima, 0x10);
+  assert_eq!(cpu.bus.interrupts.flag, 0);
+
+  // Writing during the reload M-cycle is ignored.
+  let mut cpu = cpu_before_overflow(&program, 0x0C - 4);
+  cpu.reg.a = 0x10;
+  cpu.step().unwrap();
+  assert_eq!(cpu.bus.timer.tima, 0x42);
+  assert_eq!(cpu.bus.interrupts.flag, Interrupt::Timer.bit());
+}
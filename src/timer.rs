@@ -0,0 +1,117 @@
+use crate::interrupts::{Interrupt, InterruptController};
+
+pub const DIV_ADDR: u16 = 0xFF04;
+pub const TIMA_ADDR: u16 = 0xFF05;
+pub const TMA_ADDR: u16 = 0xFF06;
+pub const TAC_ADDR: u16 = 0xFF07;
+
+const TAC_ENABLE: u8 = 1 << 2;
+
+/// DIV, TIMA, TMA and TAC.
+///
+/// DIV is the upper byte of a 16-bit counter incremented every T-cycle.
+/// TIMA increments on the falling edge of (TAC enable AND the divider bit
+/// selected by TAC), so resetting DIV or writing TAC can increment it too.
+/// When TIMA overflows it reads 0x00 for one M-cycle, then it's reloaded
+/// from TMA and the timer interrupt is requested.
+#[derive(Debug, Default)]
+pub struct Timer {
+  pub divider: u16,
+  pub tima: u8,
+  pub tma: u8,
+  pub tac: u8,
+  // TIMA overflowed during the last M-cycle, the reload happens on the next.
+  overflow: bool,
+  // TIMA was reloaded during the current M-cycle.
+  reloading: bool,
+}
+
+impl Timer {
+  pub fn new() -> Timer {
+    Timer::default()
+  }
+
+  /// Divider bit selected by TAC: 4096, 262144, 65536 or 16384 Hz.
+  fn bit(&self) -> u16 {
+    match self.tac & 0b11 {
+      0b00 => 1 << 9,
+      0b01 => 1 << 3,
+      0b10 => 1 << 5,
+      _ => 1 << 7,
+    }
+  }
+
+  fn signal(&self) -> bool {
+    self.tac & TAC_ENABLE != 0 && self.divider & self.bit() != 0
+  }
+
+  fn increment(&mut self) {
+    let (tima, overflow) = self.tima.overflowing_add(1);
+    self.tima = tima;
+    self.overflow = overflow;
+  }
+
+  /// Updates `divider` and applies the falling edge it causes, if any.
+  fn set_divider(&mut self, divider: u16) {
+    let old = self.signal();
+    self.divider = divider;
+    if old && !self.signal() {
+      self.increment();
+    }
+  }
+
+  /// Advances the timer by `t_cycles`, one M-cycle at a time.
+  pub fn tick(&mut self, t_cycles: usize, interrupts: &mut InterruptController) {
+    for _ in 0..t_cycles / 4 {
+      self.reloading = false;
+      if self.overflow {
+        self.overflow = false;
+        self.reloading = true;
+        self.tima = self.tma;
+        interrupts.request(Interrupt::Timer);
+      }
+
+      self.set_divider(self.divider.wrapping_add(4));
+    }
+  }
+
+  /// Reads DIV, TIMA, TMA or TAC.
+  pub fn read(&self, addr: u16) -> u8 {
+    match addr {
+      DIV_ADDR => (self.divider >> 8) as u8,
+      TIMA_ADDR => self.tima,
+      TMA_ADDR => self.tma,
+      // Upper 5 bits are unused and read as 1.
+      _ => self.tac | 0xF8,
+    }
+  }
+
+  /// Writes DIV, TIMA, TMA or TAC.
+  pub fn write(&mut self, addr: u16, data: u8) {
+    match addr {
+      // Any write resets the whole divider.
+      DIV_ADDR => self.set_divider(0),
+      TIMA_ADDR => {
+        // Writing during the overflow cycle cancels the reload, writing
+        // during the reload cycle is ignored.
+        if !self.reloading {
+          self.tima = data;
+          self.overflow = false;
+        }
+      }
+      TMA_ADDR => {
+        self.tma = data;
+        if self.reloading {
+          self.tima = data;
+        }
+      }
+      _ => {
+        let old = self.signal();
+        self.tac = data & 0b111;
+        if old && !self.signal() {
+          self.increment();
+        }
+      }
+    }
+  }
+}
@@ -1,2 +1 @@
-pub mod fps_counter;
-pub mod frame_counter;
+pub mod crc32;
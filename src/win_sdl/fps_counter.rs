@@ -28,3 +28,9 @@ impl FpsCounter {
     self.fps
   }
 }
+
+impl Default for FpsCounter {
+  fn default() -> FpsCounter {
+    FpsCounter::new()
+  }
+}
@@ -1,4 +1,4 @@
-// utils/frame_counter.rs
+// win_sdl/frame_counter.rs
 use std::time::Instant;
 
 pub const FRAME_HISTORY_COUNT: usize = 100;
@@ -33,3 +33,9 @@ impl FrameCounter {
     avg_frame_time
   }
 }
+
+impl Default for FrameCounter {
+  fn default() -> FrameCounter {
+    FrameCounter::new()
+  }
+}
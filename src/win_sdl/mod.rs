@@ -1,10 +1,26 @@
 #![allow(dead_code)]
-use sdl2::pixels::Color;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::ttf::{self, Sdl2TtfContext};
 use sdl2::{video::Window, EventPump, Sdl};
 
-use crate::cpu::Cpu;
+use gameboy_emulator::bus::Bus;
+use gameboy_emulator::cartridge::{CartridgeHeader, CgbFlag, Destination};
+use gameboy_emulator::cpu::Cpu;
+use gameboy_emulator::memory::MemoryInterface;
+use gameboy_emulator::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub mod fps_counter;
+pub mod frame_counter;
+
+// RGB of shades 0 (white) to 3 (black).
+const SHADES: [[u8; 3]; 4] = [
+  [0xE0, 0xF8, 0xD0],
+  [0x88, 0xC0, 0x70],
+  [0x34, 0x68, 0x56],
+  [0x08, 0x18, 0x20],
+];
 
 pub struct WinSDL {
   pub sdl: Sdl,
@@ -53,13 +69,32 @@ impl WinSDL {
       .create_texture_from_surface(&surface)
       .expect("Failed to create texture");
 
-    let target = sdl2::rect::Rect::new(x, y, surface.width(), surface.height());
+    let target = Rect::new(x, y, surface.width(), surface.height());
+    self.canvas.copy(&texture, None, Some(target)).unwrap();
+  }
+
+  // Draws the PPU framebuffer `scale` times its size.
+  pub fn draw_screen(&mut self, framebuffer: &[u8], x: i32, y: i32, scale: u32) {
+    let texture_creator = self.canvas.texture_creator();
+    let mut texture = texture_creator
+      .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+      .expect("Failed to create texture");
+
+    let pixels: Vec<u8> = framebuffer
+      .iter()
+      .flat_map(|&shade| SHADES[shade as usize & 0b11])
+      .collect();
+    texture
+      .update(None, &pixels, SCREEN_WIDTH * 3)
+      .expect("Failed to update texture");
+
+    let target = Rect::new(x, y, SCREEN_WIDTH as u32 * scale, SCREEN_HEIGHT as u32 * scale);
     self.canvas.copy(&texture, None, Some(target)).unwrap();
   }
 
   pub fn draw_memory_view(
     &mut self,
-    memory: &[u8],
+    bus: &Bus,
     address: u16,
     x: i32,
     y: i32,
@@ -73,8 +108,8 @@ impl WinSDL {
       let mut offset = format!("{:04X}:", addr);
 
       for _col in 0..=n_coll {
-        offset += &format!(" {:02X}", memory[addr as usize]);
-        addr += 1;
+        offset += &format!(" {:02X}", bus.read(addr));
+        addr = addr.wrapping_add(1);
       }
 
       self.draw_text(&offset, x, n_row_y);
@@ -85,8 +120,8 @@ impl WinSDL {
   pub fn draw_cpu_registers(&mut self, cpu: &Cpu, x: i32, y: i32) {
     let space = 20;
 
-    self.draw_text(&format!("A: {:02X}", cpu.reg.a), x, y + space * 0);
-    self.draw_text(&format!("B: {:02X}", cpu.reg.b), x, y + space * 1);
+    self.draw_text(&format!("A: {:02X}", cpu.reg.a), x, y);
+    self.draw_text(&format!("B: {:02X}", cpu.reg.b), x, y + space);
     self.draw_text(&format!("C: {:02X}", cpu.reg.c), x, y + space * 2);
     self.draw_text(&format!("D: {:02X}", cpu.reg.d), x, y + space * 3);
     self.draw_text(&format!("E: {:02X}", cpu.reg.e), x, y + space * 4);
@@ -97,25 +132,25 @@ impl WinSDL {
     self.draw_text(&format!("PC: {:04X}", cpu.reg.pc), x, y + space * 9);
 
     self.draw_text(
-      if cpu.get_flag(crate::cpu::Flags::Z) == 1 {
+      if cpu.get_flag(gameboy_emulator::cpu::Flags::Z) == 1 {
         "Z"
       } else {
         "-"
       },
-      x + 100 + space * 0,
+      x + 100,
       y,
     );
     self.draw_text(
-      if cpu.get_flag(crate::cpu::Flags::N) == 1 {
+      if cpu.get_flag(gameboy_emulator::cpu::Flags::N) == 1 {
         "N"
       } else {
         "-"
       },
-      x + 100 + space * 1,
+      x + 100 + space,
       y,
     );
     self.draw_text(
-      if cpu.get_flag(crate::cpu::Flags::H) == 1 {
+      if cpu.get_flag(gameboy_emulator::cpu::Flags::H) == 1 {
         "H"
       } else {
         "-"
@@ -124,7 +159,7 @@ impl WinSDL {
       y,
     );
     self.draw_text(
-      if cpu.get_flag(crate::cpu::Flags::C) == 1 {
+      if cpu.get_flag(gameboy_emulator::cpu::Flags::C) == 1 {
         "C"
       } else {
         "-"
@@ -134,6 +169,39 @@ impl WinSDL {
     );
   }
 
+  pub fn draw_cartridge_header(&mut self, header: &CartridgeHeader, x: i32, y: i32) {
+    let space = 20;
+
+    let cgb = match header.cgb_flag {
+      CgbFlag::None => "no",
+      CgbFlag::Supported => "yes",
+      CgbFlag::Only => "only",
+    };
+    let destination = match header.destination {
+      Destination::Japan => "Japan",
+      Destination::Overseas => "Overseas",
+    };
+    let check = |ok: bool| if ok { "ok" } else { "bad" };
+
+    let lines = [
+      format!("Title: {}", header.title),
+      format!("Maker: {}", header.manufacturer_code.as_deref().unwrap_or("-")),
+      format!("Licensee: {}", header.licensee_code),
+      format!("Type: {}", header.cartridge_type),
+      format!("ROM: {} KiB", header.rom_size / 1024),
+      format!("RAM: {} KiB", header.ram_size / 1024),
+      format!("CGB: {} SGB: {}", cgb, if header.sgb_flag { "yes" } else { "no" }),
+      format!("Dest: {}", destination),
+      format!("Version: {:02X}", header.version),
+      format!("Header: {:02X} {}", header.header_checksum, check(header.header_checksum_ok())),
+      format!("Global: {:04X} {}", header.global_checksum, check(header.global_checksum_ok())),
+    ];
+
+    for (i, line) in lines.iter().enumerate() {
+      self.draw_text(line, x, y + space * i as i32);
+    }
+  }
+
   pub fn bytes_to_ascii(&self, bytes: &[u8]) -> String {
     // Filtra os bytes para garantir que estejam no intervalo de caracteres ASCII imprimíveis
     bytes[0..=255]
@@ -169,8 +237,8 @@ impl WinSDL {
 // debugger.canvas.clear();
 
 // debugger.draw_cpu_registers(&cpu, 10, 10);
-// debugger.draw_memory_view(&bus.memory, 0x0000, 10, 300, 16, 15);
-// debugger.draw_memory_view(&bus.memory, cpu.reg.pc, 10, 230, 0, 6);
+// debugger.draw_memory_view(&bus, 0x0000, 10, 300, 16, 15);
+// debugger.draw_memory_view(&bus, cpu.reg.pc, 10, 230, 0, 6);
 
 // // debugger.draw_ascii_grid(&bus.memory, 10, 850, 300);
 